@@ -0,0 +1,128 @@
+/*
+Copyright 2022 rev1e
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+   http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::fmt;
+
+use crate::{bot::Bot, config::Config, game::{Game, Status, stats::BoardStats}, strategy};
+
+// losses are grouped by how much of the board was cleared, in tenths
+const BUCKETS: usize = 10;
+
+// one game played by the bot
+#[derive(Debug, Clone)]
+pub struct GameResult {
+    pub seed: u64,
+    pub won: bool,
+    pub three_bv: u32,
+    pub moves: u32,
+    pub guesses: u32,
+    // share of safe cells revealed before the last move
+    pub progress: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    pub config: Config,
+    pub results: Vec<GameResult>,
+}
+
+// play games on seeds config.seed, config.seed + 1, ... without any i/o
+pub fn run(config: &Config, games: u64) -> BenchReport {
+    let results = (0..games)
+        .map(|i| {
+            let config = Config { seed: config.seed.wrapping_add(i), ..config.clone() };
+            play(&config)
+        })
+        .collect();
+
+    BenchReport { config: config.clone(), results }
+}
+
+fn play(config: &Config) -> GameResult {
+    let mut game = Game::new(config);
+    let mut bot = Bot::new();
+    let result = strategy::play(&mut game, &mut bot);
+
+    GameResult {
+        seed: config.seed,
+        won: result.status == Status::Win,
+        three_bv: BoardStats::new(&game).map_or(0, |stats| stats.three_bv),
+        moves: result.moves,
+        guesses: bot.guesses,
+        progress: result.progress,
+    }
+}
+
+impl BenchReport {
+    pub fn wins(&self) -> usize {
+        self.results.iter().filter(|r| r.won).count()
+    }
+
+    // losses by share of the board cleared when the mine was hit
+    pub fn failure_points(&self) -> [usize; BUCKETS] {
+        let mut buckets = [0; BUCKETS];
+        for result in self.results.iter().filter(|r| !r.won) {
+            let bucket = ((result.progress * BUCKETS as f64) as usize).min(BUCKETS - 1);
+            buckets[bucket] += 1;
+        }
+        buckets
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let config = &self.config;
+        let games = self.results.len();
+
+        writeln!(f, "{}x{}, {} mines{}", config.width, config.height, config.mines,
+            if config.no_guess { ", no guess" } else { "" })?;
+        writeln!(f, "seeds {}..{}", config.seed, config.seed.wrapping_add(games as u64))?;
+
+        if games == 0 {
+            return Ok(());
+        }
+
+        let wins = self.wins();
+        let average = |value: fn(&GameResult) -> f64| {
+            self.results.iter().map(value).sum::<f64>() / games as f64
+        };
+
+        writeln!(f, "won {}/{} ({:.1}%)", wins, games, wins as f64 * 100.0 / games as f64)?;
+        writeln!(f, "average 3BV: {:.1}", average(|r| r.three_bv as f64))?;
+        writeln!(f, "average moves: {:.1}", average(|r| r.moves as f64))?;
+        writeln!(f, "average guesses: {:.2}", average(|r| r.guesses as f64))?;
+
+        // a move stands in for a second, so this is 3BV/s at one click per second
+        let won: Vec<&GameResult> = self.results.iter().filter(|r| r.won).collect();
+        if !won.is_empty() {
+            let rate = won.iter().map(|r| r.three_bv as f64 / r.moves as f64).sum::<f64>() / won.len() as f64;
+            writeln!(f, "3BV per move in won games: {:.2}", rate)?;
+        }
+
+        let losses = games - wins;
+        if losses == 0 {
+            return Ok(());
+        }
+
+        writeln!(f, "losses by board cleared:")?;
+        for (i, &count) in self.failure_points().iter().enumerate() {
+            let bar = "#".repeat((count * 40).div_ceil(losses));
+            writeln!(f, "{:>3}-{:>3}% {:>6} {}", i * 10, (i + 1) * 10, count, bar)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+/*
+Copyright 2022 rev1e
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+   http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::{game::{Action, position::Position}, strategy::{BoardView, Strategy, Visible}};
+
+// built-in player: cells the solver proves safe first, otherwise the
+// hidden cell least likely to be a mine. never flags
+#[derive(Default)]
+pub struct Bot {
+    // safe cells from the last solve, revealed one per move
+    pending: Vec<Position>,
+    // moves that could hit a mine
+    pub guesses: u32,
+}
+
+impl Bot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Strategy for Bot {
+    fn name(&self) -> &str {
+        "probability"
+    }
+
+    fn start(&mut self) {
+        self.pending.clear();
+        self.guesses = 0;
+    }
+
+    fn next_action(&mut self, view: &BoardView) -> Action {
+        // first click is always safe
+        if !view.started() {
+            return Action::Reveal(center(view));
+        }
+
+        if let Some(pos) = next_safe(&mut self.pending, view) {
+            return Action::Reveal(pos);
+        }
+
+        // exact probabilities also find safe cells the solver misses.
+        // without flags they always fit the board
+        let (pos, risk) = match view.probabilities() {
+            Some(probs) => hidden(view)
+                .map(|pos| (pos, probs.get(pos).unwrap()))
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap()),
+            None => hidden(view).next().map(|pos| (pos, 1.0)),
+        }.expect("game running without hidden cells");
+
+        if risk > 0.0 {
+            self.guesses += 1;
+        }
+
+        Action::Reveal(pos)
+    }
+}
+
+// baseline player: solver deductions, otherwise a random hidden cell
+pub struct RandomBot {
+    pending: Vec<Position>,
+    rng: StdRng,
+}
+
+impl RandomBot {
+    pub fn new(seed: u64) -> Self {
+        Self { pending: Vec::new(), rng: StdRng::seed_from_u64(seed) }
+    }
+}
+
+impl Strategy for RandomBot {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn start(&mut self) {
+        self.pending.clear();
+    }
+
+    fn next_action(&mut self, view: &BoardView) -> Action {
+        if !view.started() {
+            return Action::Reveal(center(view));
+        }
+
+        if let Some(pos) = next_safe(&mut self.pending, view) {
+            return Action::Reveal(pos);
+        }
+
+        let cells: Vec<Position> = hidden(view).collect();
+        Action::Reveal(cells[self.rng.gen_range(0..cells.len())])
+    }
+}
+
+fn center(view: &BoardView) -> Position {
+    Position::new(view.width() / 2, view.height() / 2)
+}
+
+// safe cell still hidden, solving again when pending runs out.
+// earlier reveals may have opened some of them already
+fn next_safe(pending: &mut Vec<Position>, view: &BoardView) -> Option<Position> {
+    for _ in 0..2 {
+        while let Some(pos) = pending.pop() {
+            if view.get(pos) == Visible::Hidden {
+                return Some(pos);
+            }
+        }
+
+        *pending = view.deductions()
+            .into_iter()
+            .filter(|d| !d.mine)
+            .map(|d| d.pos)
+            .rev()
+            .collect();
+    }
+
+    None
+}
+
+// hidden cells without a flag, row by row
+fn hidden<'v>(view: &'v BoardView) -> impl Iterator<Item = Position> + 'v {
+    (0..view.height())
+        .flat_map(move |y| (0..view.width()).map(move |x| Position::new(x, y)))
+        .filter(move |&pos| matches!(view.get(pos), Visible::Hidden | Visible::Question))
+}
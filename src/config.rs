@@ -14,56 +14,126 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
+use std::str::FromStr;
+
 use clap::ArgMatches;
 
-use crate::display::LETTERS;
+use crate::error::ConfigError;
+
+const MIN_WIDTH: usize = 2;
+const MIN_HEIGHT: usize = 2;
+// up to two letter columns (ZZ) and three digit rows
+const MAX_WIDTH: usize = 26 * 27;
+const MAX_HEIGHT: usize = 1000;
+// percentage of mines no-guess boards are still found at quickly.
+// on 30x16 that's about 20 attempts, at 26% most boards give up
+const MAX_NO_GUESS_DENSITY: usize = 22;
 
-#[derive(Debug)]
+// name, width, height, mines
+const PRESETS: [(&str, usize, usize, u32); 3] = [
+    ("beginner", 9, 9, 10),
+    ("intermediate", 16, 16, 40),
+    ("expert", 30, 16, 99),
+];
+
+#[derive(Debug, Clone)]
 pub struct Config {
     pub width: usize,
     pub height: usize,
     pub mines: u32,
+    pub seed: u64,
+    // only accept boards that can be solved without guessing
+    pub no_guess: bool,
+    // revealing a revealed number chords it
+    pub implicit_chord: bool,
+    // flagging a flag turns it into a question mark
+    pub question_marks: bool,
 }
 
 impl Config {
-    pub fn new(args: ArgMatches) -> Result<Self, String> {
-        let width = args.value_of("width").unwrap().parse()
-            .map_err(|_| {
-            return "width: invalid number".to_string();
-        })?;
-        let height = args.value_of("height").unwrap().parse()
-            .map_err(|_| {
-            return "height: invalid number".to_string();
-        })?;
-        let mines = args.value_of("mines").unwrap().parse()
-            .map_err(|_| {
-            return "mines: invalid number".to_string();
-        })?;
-
-        if height < 2 {
-            return Err("min height is 2".to_string());
+    pub fn new(args: &ArgMatches) -> Result<Self, ConfigError> {
+        // options given on the command line, not just defaults
+        let given = |name| args.occurrences_of(name) > 0;
+
+        if given("mines") && given("density") {
+            return Err(ConfigError::Conflict { first: "mines", second: "density" });
         }
 
-        if height > 30 {
-            return Err("max height is 30".to_string());
+        let (width, height, mut mines) = match args.value_of("preset") {
+            Some(name) => {
+                for option in ["width", "height", "mines", "density"] {
+                    if given(option) {
+                        return Err(ConfigError::Conflict { first: "preset", second: option });
+                    }
+                }
+
+                let preset = PRESETS.iter().find(|p| p.0 == name)
+                    .ok_or_else(|| ConfigError::UnknownPreset(name.to_string()))?;
+                (preset.1, preset.2, preset.3)
+            },
+            None => (
+                parse_number(args, "width")?,
+                parse_number(args, "height")?,
+                parse_number(args, "mines")?,
+            ),
+        };
+
+        // percentage of cells that are mines, at least one mine
+        if given("density") {
+            let density: f64 = parse_number(args, "density")?;
+
+            if !(0.0..100.0).contains(&density) {
+                return Err(ConfigError::DensityOutOfRange(density));
+            }
+
+            mines = ((width * height) as f64 * density / 100.0).round().max(1.0) as u32;
         }
 
-        if width < 2 {
-            return Err("min width is 2".to_string());
+        // random seed unless one was given, so every board can be replayed
+        let seed = match args.value_of("seed") {
+            Some(_) => parse_number(args, "seed")?,
+            None => rand::random(),
+        };
+
+        let config = Self {
+            width,
+            height,
+            mines,
+            seed,
+            no_guess: args.is_present("no-guess"),
+            implicit_chord: !args.is_present("no-implicit-chord"),
+            question_marks: args.is_present("question-marks"),
+        };
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !(MIN_HEIGHT..=MAX_HEIGHT).contains(&self.height) {
+            return Err(ConfigError::HeightOutOfRange { min: MIN_HEIGHT, max: MAX_HEIGHT, got: self.height });
         }
 
-        if width > LETTERS.len() {
-            return Err(format!("max width is {}", LETTERS.len()));
+        if !(MIN_WIDTH..=MAX_WIDTH).contains(&self.width) {
+            return Err(ConfigError::WidthOutOfRange { min: MIN_WIDTH, max: MAX_WIDTH, got: self.width });
         }
 
-        if mines < 1 {
-            return Err("minimal number of mines is 1".to_string());
+        let max_mines = (self.width * self.height - 1) as u32;
+        if !(1..=max_mines).contains(&self.mines) {
+            return Err(ConfigError::MinesOutOfRange { min: 1, max: max_mines, got: self.mines });
         }
 
-        if mines as usize > (width * height) - 1 {
-            return Err(format!("max number of mines is {}", (width * height) - 1));
+        if self.no_guess && self.mines as usize * 100 > self.width * self.height * MAX_NO_GUESS_DENSITY {
+            let max = (self.width * self.height * MAX_NO_GUESS_DENSITY / 100) as u32;
+            return Err(ConfigError::NoGuessTooDense { max, got: self.mines });
         }
 
-        Ok(Self { width, height, mines })
+        Ok(())
     }
 }
+
+fn parse_number<T: FromStr>(args: &ArgMatches, field: &'static str) -> Result<T, ConfigError> {
+    let value = args.value_of(field).unwrap();
+    value.parse().map_err(|_| ConfigError::InvalidNumber { field, value: value.to_string() })
+}
@@ -14,13 +14,11 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
-use std::io::{self, Write};
+use std::{io::{self, Write}, time::Duration};
 
-use colored::Colorize;
+use colored::{ColoredString, Colorize};
 
-use crate::{game::{cell::{Cells, CellType}, position::Position}, config::Config};
-
-pub const LETTERS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+use crate::{game::{cell::{Cell, Cells, CellType}, position::{Position, column_label}, probability::Probabilities}, config::Config};
 
 // handles i/o
 pub struct Display<'a> {
@@ -38,9 +36,9 @@ impl<'a> Display<'a> {
         print!("{esc}[2J{esc}[1;1H", esc = 27 as char);
     }
 
-    pub fn get_input(&self, mines_left: i32) -> String {
+    pub fn get_input(&self, mines_left: i32, elapsed: Duration) -> String {
         // print prompt
-        print!("({} mines left) -> ", mines_left);
+        print!("({} mines left, {}) -> ", mines_left, format_time(elapsed));
         io::stdout().lock().flush().unwrap();
 
         let mut input = String::new();
@@ -52,70 +50,200 @@ impl<'a> Display<'a> {
         input
     }
 
-    pub fn render_board(&self, map: &Cells) {
-        print!("     ");
-        for x in 0..(self.config.width - 1) {
-            print!("{} ", LETTERS.chars().nth(x).unwrap());
+    // none once stdin is closed
+    pub fn prompt(&self, msg: &str) -> Option<String> {
+        print!("{}", msg);
+        io::stdout().lock().flush().unwrap();
+
+        let mut input = String::new();
+        match io::stdin().read_line(&mut input) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(input.trim().to_string()),
+        }
+    }
+
+    // marked cell is shown in reverse video
+    pub fn render_board(&self, map: &Cells, marked: Option<Position>) {
+        let lines = self.lines_with(|pos| {
+            let cell = self.cell_str(map.idx(pos));
+            if marked == Some(pos) { cell.reversed() } else { cell }
+        });
+
+        for line in lines {
+            println!("{}", line);
         }
-        println!("{}", LETTERS.chars().nth(self.config.width - 1).unwrap());
+    }
 
-        print!("     ");
-        for _ in 0..(self.config.width * 2) {
-            print!("-");
+    // flags in the color of the player who placed them
+    pub fn render_owned_flags(&self, map: &Cells, owners: &[Option<usize>]) {
+        let lines = self.lines_with(|pos| {
+            match owners[pos.y * self.config.width + pos.x] {
+                Some(player) if map.idx(pos).flag => player_color("!", player),
+                _ => self.cell_str(map.idx(pos)),
+            }
+        });
+
+        for line in lines {
+            println!("{}", line);
         }
-        print!("\n");
+    }
+
+    // heatmap of mine probabilities over the board
+    pub fn render_probabilities(&self, map: &Cells, probs: &Probabilities) {
+        for line in self.lines_with(|pos| self.heat_str(map.idx(pos), probs.get(pos))) {
+            println!("{}", line);
+        }
+        println!("S safe, M mine, 0-9 chance of a mine in tenths");
+    }
+
+    // board with column and row labels, one string per line
+    pub fn board_lines(&self, map: &Cells) -> Vec<String> {
+        self.lines_with(|pos| self.cell_str(map.idx(pos)))
+    }
+
+    fn lines_with(&self, cell: impl Fn(Position) -> ColoredString) -> Vec<String> {
+        let row_width = self.row_label_width();
+        let mut lines = self.column_lines();
+
+        lines.push(self.separator());
 
         for y in 0..self.config.height {
-            print!("{:02} | ", y);
+            let mut line = format!("{:0w$} | ", y, w = row_width);
             for x in 0..self.config.width {
-                let cell = map.idx(Position::new(x, y));
-                
-                if cell.flag {
-                    print!("{} ", "!".red());
-                    continue;
-                }
+                line += &format!("{} ", cell(Position::new(x, y)));
+            }
+            line += &format!("| {:0w$}", y, w = row_width);
+            lines.push(line);
+        }
 
-                if cell.hidden {
-                    print!("# ");
-                    continue;
-                }
+        lines.push(self.separator());
+        lines.extend(self.column_lines());
 
-                match cell.ctype {
-                    CellType::Mine => print!("{} ", "*".red()),
-                    CellType::Empty => print!("{} ", ".".bright_black()),
-                    CellType::Number(n) => {
-                        if n < 3 {
-                            print!("{} ", format!("{}", n).bright_green());
-                        } else if n < 5 {
-                            print!("{} ", format!("{}", n).yellow());
-                        } else {
-                            print!("{} ", format!("{}", n).bright_red());
-                        }
-                    }
+        lines
+    }
+
+    // one character for a cell
+    pub fn cell_str(&self, cell: Cell) -> ColoredString {
+        if cell.flag {
+            return "!".red();
+        }
+
+        if cell.question && cell.hidden {
+            return "?".bright_cyan();
+        }
+
+        if cell.hidden {
+            return "#".normal();
+        }
+
+        match cell.ctype {
+            CellType::Mine => "*".red(),
+            CellType::Empty => ".".bright_black(),
+            CellType::Number(n) => {
+                if n < 3 {
+                    n.to_string().bright_green()
+                } else if n < 5 {
+                    n.to_string().yellow()
+                } else {
+                    n.to_string().bright_red()
                 }
-            }   
-            println!("| {:02}", y);
+            }
         }
+    }
 
-        print!("     ");
-        for _ in 0..(self.config.width * 2) {
-            print!("-");
+    // cell with its chance of a mine. revealed and flagged cells
+    // look as usual
+    pub fn heat_str(&self, cell: Cell, prob: Option<f64>) -> ColoredString {
+        let p = match prob {
+            Some(p) => p,
+            None => return self.cell_str(cell),
+        };
+
+        if p < 1e-9 {
+            "S".black().on_green()
+        } else if p > 1.0 - 1e-9 {
+            "M".black().on_red()
+        } else {
+            let tenths = ((p * 10.0) as u32).min(9);
+            let s = tenths.to_string().black();
+            match tenths {
+                0..=2 => s.on_bright_green(),
+                3..=5 => s.on_yellow(),
+                _ => s.on_bright_red(),
+            }
         }
-        print!("\n");
+    }
 
-        print!("     ");
-        for x in 0..(self.config.width - 1) {
-            print!("{} ", LETTERS.chars().nth(x).unwrap());
+    // screen column and line of a cell in board_lines, 0 based
+    pub fn cell_screen_pos(&self, pos: Position) -> (usize, usize) {
+        let x = self.row_label_width() + 3 + pos.x * 2;
+        let y = column_label(self.config.width - 1).len() + 1 + pos.y;
+        (x, y)
+    }
+
+    // cell under a screen column and line, inverse of cell_screen_pos.
+    // the space right of a cell belongs to it
+    pub fn screen_to_cell(&self, x: usize, y: usize) -> Option<Position> {
+        let (left, top) = self.cell_screen_pos(Position::new(0, 0));
+
+        if x < left || y < top {
+            return None;
+        }
+
+        let pos = Position::new((x - left) / 2, y - top);
+        if pos.x >= self.config.width || pos.y >= self.config.height {
+            return None;
         }
-        println!("{}", LETTERS.chars().nth(self.config.width - 1).unwrap());
+
+        Some(pos)
+    }
+
+    // row numbers are at least two digits wide
+    fn row_label_width(&self) -> usize {
+        (self.config.height - 1).to_string().len().max(2)
     }
 
+    // column labels are written vertically, one line per letter,
+    // so every column stays one character wide
+    fn column_lines(&self) -> Vec<String> {
+        let labels: Vec<String> = (0..self.config.width).map(column_label).collect();
+        let rows = labels.last().unwrap().len();
+
+        (0..rows).map(|row| {
+            let line: Vec<String> = labels.iter()
+                .map(|label| {
+                    let pad = rows - label.len();
+                    if row < pad {
+                        " ".to_string()
+                    } else {
+                        label[row - pad..row - pad + 1].to_string()
+                    }
+                })
+                .collect();
+
+            format!("{}{}", " ".repeat(self.row_label_width() + 3), line.join(" ").trim_end())
+        }).collect()
+    }
+
+    fn separator(&self) -> String {
+        format!("{}{}", " ".repeat(self.row_label_width() + 3), "-".repeat(self.config.width * 2))
+    }
 
     pub fn print_help(&self) {
         println!("Help:");
         println!("<pos> -> guess");
         println!("f <pos>, flag <pos>, f<pos> -> flag position");
+        if self.config.question_marks {
+            println!("    flagging a flag marks it with ?, flagging ? clears it");
+        }
+        println!("c <pos>, chord <pos> -> reveal around a number with all mines flagged");
         println!("r -> reveal all possible");
+        println!("undo, u -> take back the last move");
+        println!("redo -> play the taken back move again");
+        println!("hint -> show a safe cell or mine, or the safest guess");
+        println!("probs -> show the chance of a mine for every hidden cell");
+        println!("save <file> -> save game to file");
+        println!("load <file> -> load game from file");
         println!("quit, exit, q -> exit game");
         println!("help, h, ? -> print this message");
         print!("Press enter to continue..");
@@ -123,3 +251,21 @@ impl<'a> Display<'a> {
         io::stdin().read_line(&mut String::new()).unwrap();
     }
 }
+
+// text in the color of player n, colors repeat after six players
+pub fn player_color(text: &str, player: usize) -> ColoredString {
+    match player % 6 {
+        0 => text.bright_red(),
+        1 => text.bright_blue(),
+        2 => text.bright_green(),
+        3 => text.bright_magenta(),
+        4 => text.bright_cyan(),
+        _ => text.bright_yellow(),
+    }
+}
+
+// mm:ss
+pub fn format_time(time: Duration) -> String {
+    let secs = time.as_secs();
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
@@ -0,0 +1,192 @@
+/*
+Copyright 2022 rev1e
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+   http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::{error::Error, fmt, io};
+
+use crate::game::position::Position;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    InvalidNumber { field: &'static str, value: String },
+    WidthOutOfRange { min: usize, max: usize, got: usize },
+    HeightOutOfRange { min: usize, max: usize, got: usize },
+    MinesOutOfRange { min: u32, max: u32, got: u32 },
+    // percentage of mines
+    DensityOutOfRange(f64),
+    UnknownPreset(String),
+    // two options that can't be used together
+    Conflict { first: &'static str, second: &'static str },
+    // too many mines to find a no-guess layout
+    NoGuessTooDense { max: u32, got: u32 },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::InvalidNumber { field, value } =>
+                write!(f, "{}: invalid number '{}'", field, value),
+            ConfigError::WidthOutOfRange { min, max, got } =>
+                write!(f, "width must be between {} and {}, got {}", min, max, got),
+            ConfigError::HeightOutOfRange { min, max, got } =>
+                write!(f, "height must be between {} and {}, got {}", min, max, got),
+            ConfigError::MinesOutOfRange { min, max, got } =>
+                write!(f, "number of mines must be between {} and {}, got {}", min, max, got),
+            ConfigError::DensityOutOfRange(got) =>
+                write!(f, "density must be a percentage below 100, got {}", got),
+            ConfigError::UnknownPreset(name) =>
+                write!(f, "unknown preset '{}', use beginner, intermediate or expert", name),
+            ConfigError::Conflict { first, second } =>
+                write!(f, "{} can't be used together with {}", first, second),
+            ConfigError::NoGuessTooDense { max, got } =>
+                write!(f, "no-guess boards of this size can have at most {} mines, got {}", max, got),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    // no command was entered
+    Empty,
+    UnknownCommand(String),
+    // command needs coordinates as argument
+    MissingCoordinates,
+    // command needs file name as argument
+    MissingFile,
+    BadCoordinates(String),
+    ColumnOutOfRange { max: usize, got: usize },
+    RowOutOfRange { max: usize, got: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "enter command"),
+            ParseError::UnknownCommand(name) => write!(f, "unknown command '{}'", name),
+            ParseError::MissingCoordinates => write!(f, "pass coordinates as argument"),
+            ParseError::MissingFile => write!(f, "pass file name as argument"),
+            ParseError::BadCoordinates(input) => write!(f, "bad coordinates '{}'", input),
+            ParseError::ColumnOutOfRange { max, got } =>
+                write!(f, "x is too big ({} > {})", got, max),
+            ParseError::RowOutOfRange { max, got } =>
+                write!(f, "y is too big ({} > {})", got, max),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MoveError {
+    // game already ended with win or loss
+    GameOver,
+    Flagged(Position),
+    NotHidden(Position),
+    Hidden(Position),
+    // only revealed numbers can be chorded
+    NotANumber(Position),
+    // number at pos doesn't have as many flags around as it says
+    FlagCountMismatch { pos: Position, number: u32, flags: u32 },
+    NothingToUndo,
+    NothingToRedo,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::GameOver => write!(f, "game is over"),
+            MoveError::Flagged(pos) => write!(f, "there is a flag on {}", pos),
+            MoveError::NotHidden(pos) => write!(f, "{} is not hidden", pos),
+            MoveError::Hidden(pos) => write!(f, "{} is hidden", pos),
+            MoveError::NotANumber(pos) => write!(f, "{} is not a number", pos),
+            MoveError::FlagCountMismatch { pos, number, flags } =>
+                write!(f, "{} is {} but has {} flag{} around", pos, number, flags,
+                    if *flags == 1 { "" } else { "s" }),
+            MoveError::NothingToUndo => write!(f, "nothing to undo"),
+            MoveError::NothingToRedo => write!(f, "nothing to redo"),
+        }
+    }
+}
+
+impl Error for MoveError {}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    // first line is not a save header or has an unknown version
+    BadHeader(String),
+    MissingField(&'static str),
+    InvalidValue { field: &'static str, value: String },
+    BadBoard(String),
+    Config(ConfigError),
+    // save was made for another board size or mine count
+    Mismatch { width: usize, height: usize, mines: u32 },
+    // finished games can't be saved
+    GameOver,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "{}", err),
+            SaveError::BadHeader(line) => write!(f, "not a save file: bad header '{}'", line),
+            SaveError::MissingField(field) => write!(f, "save file is missing '{}'", field),
+            SaveError::InvalidValue { field, value } =>
+                write!(f, "save file has invalid {} '{}'", field, value),
+            SaveError::BadBoard(msg) => write!(f, "save file board is corrupt: {}", msg),
+            SaveError::Config(err) => write!(f, "save file config is invalid: {}", err),
+            SaveError::Mismatch { width, height, mines } =>
+                write!(f, "save is for a {}x{} board with {} mines", width, height, mines),
+            SaveError::GameOver => write!(f, "game is over"),
+        }
+    }
+}
+
+impl Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(err: io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+#[derive(Debug)]
+pub enum ScoreError {
+    Io(io::Error),
+    // line of the score file that could not be read
+    Corrupt { line: usize },
+    // another game kept the score file locked
+    Locked,
+}
+
+impl fmt::Display for ScoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScoreError::Io(err) => write!(f, "{}", err),
+            ScoreError::Corrupt { line } => write!(f, "score file is corrupt at line {}", line),
+            ScoreError::Locked => write!(f, "score file is locked by another game"),
+        }
+    }
+}
+
+impl Error for ScoreError {}
+
+impl From<io::Error> for ScoreError {
+    fn from(err: io::Error) -> Self {
+        ScoreError::Io(err)
+    }
+}
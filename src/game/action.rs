@@ -0,0 +1,71 @@
+/*
+Copyright 2022 rev1e
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+   http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::{fmt, str::FromStr};
+
+use crate::error::ParseError;
+
+use super::position::Position;
+
+// single move on the board
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Action {
+    Reveal(Position),
+    Flag(Position),
+    Chord(Position),
+    RevealPossible,
+    Undo,
+    Redo,
+}
+
+// reveal E5, flag A0, chord B3, possible, undo, redo
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Reveal(pos) => write!(f, "reveal {}", pos),
+            Action::Flag(pos) => write!(f, "flag {}", pos),
+            Action::Chord(pos) => write!(f, "chord {}", pos),
+            Action::RevealPossible => write!(f, "possible"),
+            Action::Undo => write!(f, "undo"),
+            Action::Redo => write!(f, "redo"),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = ParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut args = input.split_whitespace();
+        let name = args.next().ok_or(ParseError::Empty)?;
+
+        match name {
+            "possible" => return Ok(Action::RevealPossible),
+            "undo" => return Ok(Action::Undo),
+            "redo" => return Ok(Action::Redo),
+            _ => {},
+        }
+
+        let pos = args.next().ok_or(ParseError::MissingCoordinates)?.parse()?;
+
+        match name {
+            "reveal" => Ok(Action::Reveal(pos)),
+            "flag" => Ok(Action::Flag(pos)),
+            "chord" => Ok(Action::Chord(pos)),
+            _ => Err(ParseError::UnknownCommand(name.to_string())),
+        }
+    }
+}
@@ -14,11 +14,14 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
-use rand::Rng;
+use rand::{Rng, SeedableRng, rngs::StdRng};
 
 use crate::config::Config;
 
-use super::position::Position;
+use super::{position::Position, solver};
+
+// generated boards to try before giving up on a no-guess layout
+const NO_GUESS_ATTEMPTS: u32 = 5000;
 
 pub enum RevealResult {
     Mine,
@@ -33,10 +36,12 @@ pub enum CellType {
     Empty,
 }
 
-#[derive(Copy,Debug,Clone)]
+#[derive(Copy,Debug,Clone,PartialEq)]
 pub struct Cell {
     pub hidden: bool,
     pub flag: bool,
+    // "maybe a mine" mark, doesn't count as a flag
+    pub question: bool,
     pub ctype: CellType,
 }
 
@@ -45,14 +50,22 @@ impl Cell {
         Self {
             hidden: true,
             flag: false,
-            ctype: CellType::Mine
+            question: false,
+            ctype: CellType::Empty
         }
     }
 }
 
+#[derive(Clone)]
 pub struct Cells<'a> {
     data: Vec<Cell>,
     config: &'a Config,
+    // mines are placed on the first reveal
+    generated: bool,
+    // no-guess layout that was checked to be solvable by deduction
+    guess_free: bool,
+    seed: u64,
+    rng: StdRng,
 }
 
 impl<'a> Cells<'a> {
@@ -60,31 +73,100 @@ impl<'a> Cells<'a> {
         assert!(config.width > 1);
         assert!(config.height > 1);
 
-        let mut cells = Self {
+        Self {
             data: vec![Cell::new(); config.width * config.height],
             config,
-        };
+            generated: false,
+            guess_free: false,
+            seed: config.seed,
+            rng: StdRng::seed_from_u64(config.seed),
+        }
+    }
+
+    // replace the board with saved cells. numbers are recalculated from mines
+    pub fn restore(&mut self, data: Vec<Cell>, generated: bool, guess_free: bool, seed: u64) {
+        assert!(data.len() == self.config.width * self.config.height);
+
+        self.data = data;
+        for cell in &mut self.data {
+            if let CellType::Number(_) = cell.ctype {
+                cell.ctype = CellType::Empty;
+            }
+        }
+
+        if generated {
+            self.generate_numbers();
+        }
+
+        self.generated = generated;
+        self.guess_free = guess_free;
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn generated(&self) -> bool {
+        self.generated
+    }
 
-        cells.generate_mines();
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
 
-        cells
+    pub fn guess_free(&self) -> bool {
+        self.guess_free
     }
 
-    fn generate_mines(&mut self) {
-        assert!(self.config.mines as usize <= self.config.width * self.config.height);
+    // place mines so that the first revealed cell and its neighbours are safe.
+    // on boards too dense for that only the first cell is kept free.
+    // in no-guess mode layouts are generated until one can be solved by
+    // deduction alone. if no attempt succeeds the last one is kept
+    // and guess_free stays false
+    fn generate_mines(&mut self, first: Position) {
+        assert!((self.config.mines as usize) < self.config.width * self.config.height);
+
+        let mut safe = self.neighbours(first);
+        safe.push(first);
+
+        let mut mine_fields: Vec<usize> = (0..(self.config.width * self.config.height))
+            .filter(|&i| !safe.iter().any(|p| self.pos_to_idx(*p) == i))
+            .collect();
+
+        if mine_fields.len() < self.config.mines as usize {
+            let first_idx = self.pos_to_idx(first);
+            mine_fields = (0..(self.config.width * self.config.height))
+                .filter(|&i| i != first_idx)
+                .collect();
+        }
+
+        let attempts = if self.config.no_guess { NO_GUESS_ATTEMPTS } else { 1 };
+
+        for _ in 0..attempts {
+            self.place_mines(mine_fields.clone());
+
+            if !self.config.no_guess {
+                break;
+            }
+
+            if solver::is_solvable(self, first) {
+                self.guess_free = true;
+                break;
+            }
+        }
+    }
 
-        let mut mine_fields: Vec<usize> =
-            (0..(self.config.width * self.config.height)).collect();
-        let mut rng = rand::thread_rng();
+    fn place_mines(&mut self, mut mine_fields: Vec<usize>) {
+        for cell in &mut self.data {
+            cell.ctype = CellType::Empty;
+        }
 
-        // all cells are mines by default
-        for _ in 0..(self.config.width * self.config.height - self.config.mines as usize) {
-            let idx = rng.gen_range(0..mine_fields.len());
-            self.data[mine_fields[idx]].ctype = CellType::Empty;
+        for _ in 0..self.config.mines {
+            let idx = self.rng.gen_range(0..mine_fields.len());
+            self.data[mine_fields[idx]].ctype = CellType::Mine;
             mine_fields.remove(idx);
         }
 
         self.generate_numbers();
+        self.generated = true;
     }
 
     fn generate_numbers(&mut self) {
@@ -135,6 +217,10 @@ impl<'a> Cells<'a> {
     }
 
     pub fn reveal(&mut self, pos: Position) -> RevealResult {
+        if !self.generated {
+            self.generate_mines(pos);
+        }
+
         let cell = self.idx(pos);
 
         if !cell.hidden {
@@ -170,7 +256,10 @@ impl<'a> Cells<'a> {
             return;
         }
 
-        self.idx_mut(pos).hidden = false;
+        // question marks don't stop the flood fill
+        let cell_mut = self.idx_mut(pos);
+        cell_mut.hidden = false;
+        cell_mut.question = false;
 
         if let CellType::Number(_) = cell.ctype {
             return;
@@ -211,7 +300,8 @@ impl<'a> Cells<'a> {
         }
     }
 
-    fn reveal_visible(&mut self, pos: Position) -> RevealResult {
+    // reveal neighbours of a revealed number when all of its mines are flagged
+    pub fn reveal_visible(&mut self, pos: Position) -> RevealResult {
         if !self.reveal_visible_r(pos) {
             return RevealResult::Mine;
         }
@@ -237,6 +327,7 @@ impl<'a> Cells<'a> {
         }
 
         cell.hidden = false;
+        cell.question = false;
 
         macro_rules! idx_flag {
             ($a:expr,$b:expr) => (self.idx(Position::new($a, $b)).flag)
@@ -316,7 +407,7 @@ impl<'a> Cells<'a> {
         true
     }
 
-    fn check_win(&self) -> bool {
+    pub fn check_win(&self) -> bool {
         for cell in &self.data {
             if cell.hidden && cell.ctype != CellType::Mine {
                 return false;
@@ -331,31 +422,56 @@ impl<'a> Cells<'a> {
                 let cell = self.idx_mut(Position::new(x, y));
                 cell.hidden = false;
                 cell.flag = false;
+                cell.question = false;
             }
         }
     }
 
-    pub fn idx(&self, pos: Position) -> Cell {
-        assert!(pos.x < self.config.width);
-        assert!(pos.y < self.config.height);
+    pub fn config(&self) -> &'a Config {
+        self.config
+    }
 
-        self.data[pos.y * self.config.width + pos.x]
+    pub fn idx(&self, pos: Position) -> Cell {
+        self.data[self.pos_to_idx(pos)]
     }
 
     pub fn idx_mut(&mut self, pos: Position) -> &mut Cell {
+        let idx = self.pos_to_idx(pos);
+        &mut self.data[idx]
+    }
+
+    fn pos_to_idx(&self, pos: Position) -> usize {
         assert!(pos.x < self.config.width);
         assert!(pos.y < self.config.height);
 
-        &mut self.data[pos.y * self.config.width + pos.x]
+        pos.y * self.config.width + pos.x
+    }
+
+    // positions of all cells around pos that are inside the board
+    pub fn neighbours(&self, pos: Position) -> Vec<Position> {
+        pos.neighbours(self.config.width, self.config.height)
     }
 
+    pub fn flags_around(&self, pos: Position) -> u32 {
+        self.neighbours(pos).into_iter().filter(|&p| self.idx(p).flag).count() as u32
+    }
+
+    // cycle none -> flag -> question mark (if enabled) -> none
     pub fn flag_cell(&mut self, pos: Position) {
+        let question_marks = self.config.question_marks;
         let cell = self.idx_mut(pos);
 
         if !cell.hidden {
             return;
         }
 
-        cell.flag = !cell.flag;
+        if cell.flag {
+            cell.flag = false;
+            cell.question = question_marks;
+        } else if cell.question {
+            cell.question = false;
+        } else {
+            cell.flag = true;
+        }
     }
 }
@@ -14,168 +14,376 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 
-use colored::Colorize;
+use std::time::Duration;
+
 use lazy_static::lazy_static;
 use regex::Regex;
 
-use crate::{display::{Display, LETTERS}, game::cell::RevealResult, config::Config};
+use crate::{game::cell::RevealResult, config::Config, error::{MoveError, ParseError, SaveError}, save::SaveFile};
 
-use self::{cell::{Cells, CellType}, position::Position};
+use self::{cell::{Cell, Cells, CellType}, position::Position, probability::Probabilities, solver::Hint, timer::Timer};
 
+pub mod action;
 pub mod cell;
 pub mod position;
+pub mod probability;
+pub mod solver;
+pub mod stats;
+pub mod timer;
+
+pub use self::action::Action;
+
+// cells kept over all undo snapshots, older moves can't be taken back
+const MAX_UNDO_CELLS: usize = 1_000_000;
 
-enum EventType {
-    GameOver,
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Status {
+    Normal,
     Win,
-    Error(String),
+    Loss,
+}
+
+// result of a move: game status and cells whose state changed
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub status: Status,
+    pub changed: Vec<Position>,
 }
 
+// state before a move, for undo and redo
+#[derive(Clone)]
+struct Snapshot<'a> {
+    map: Cells<'a>,
+    status: Status,
+    mines_left: i32,
+}
+
+// game state machine without any i/o
 pub struct Game<'a> {
     map: Cells<'a>,
-    display: Display<'a>,
-    event: Option<EventType>,
+    status: Status,
     mines_left: i32,
     config: &'a Config,
+    // runs from the first reveal until the game ends
+    timer: Timer,
+    undo: Vec<Snapshot<'a>>,
+    redo: Vec<Snapshot<'a>>,
+    // moves are remembered for undo
+    history: bool,
+    // undo was used, game doesn't count for high scores
+    assisted: bool,
+    // hints asked for
+    hints: u32,
+    // moves that changed the board, not counting undo and redo
+    clicks: u32,
 }
 
 impl<'a> Game<'a> {
     pub fn new(config: &'a Config) -> Self {
         assert!(config.mines <= i32::MAX as u32);
         Self {
-            display: Display::new(&config),
-            map: Cells::new(&config),
-            event: None,
+            map: Cells::new(config),
+            status: Status::Normal,
             mines_left: config.mines as i32,
             config,
+            timer: Timer::new(Duration::ZERO),
+            undo: Vec::new(),
+            redo: Vec::new(),
+            history: true,
+            assisted: false,
+            hints: 0,
+            clicks: 0,
         }
     }
 
-    pub fn run(&mut self) {
-        // game loop
-        loop {
-            self.display.clear_screen();
-            self.display.render_board(&self.map);
-
-            // handle event
-            if let Some(etype) = &self.event {
-                match etype {
-                    EventType::Error(msg) => println!("[ERR] {}", msg.red()),
-                    EventType::Win => {
-                        println!("{}", "!!! YOU WON !!!".bright_green());
-                        break;
-                    },
-                    EventType::GameOver => {
-                        println!("{}", "!!! GAME OVER !!!".red());
-                        break;
-                    }
-                }
-                self.event = None;
-            }
+    pub fn to_save(&self) -> Result<SaveFile, SaveError> {
+        if self.status != Status::Normal {
+            return Err(SaveError::GameOver);
+        }
 
-            let input = self.display.get_input(self.mines_left).to_lowercase();
-            
-            if input.is_empty() {
-                self.event = Some(EventType::Error("enter command".to_string()));
-                continue;
-            }
+        let mut config = self.config.clone();
+        config.seed = self.map.seed();
 
-            let mut args_iter = input.split_whitespace();
+        let cells = (0..self.config.height)
+            .flat_map(|y| (0..self.config.width).map(move |x| Position::new(x, y)))
+            .map(|pos| self.map.idx(pos))
+            .collect();
 
-            match args_iter.next().unwrap() {
-                "quit" | "exit" | "q" => break,
-                "flag" | "f" => {
-                    let pos_str = args_iter.next();
+        Ok(SaveFile {
+            config,
+            cells,
+            generated: self.map.generated(),
+            guess_free: self.map.guess_free(),
+            mines_left: self.mines_left,
+            elapsed: self.elapsed(),
+            assisted: self.assisted,
+            hints: self.hints,
+            clicks: self.clicks,
+        })
+    }
 
-                    if pos_str.is_none() {
-                        self.event = Some(EventType::Error("pass coordinates as argument".to_string()));
-                        continue;
-                    }
+    // continue a saved game. save has to be for the same board size and mines
+    pub fn restore(&mut self, save: SaveFile) -> Result<(), SaveError> {
+        if save.config.width != self.config.width
+            || save.config.height != self.config.height
+            || save.config.mines != self.config.mines {
+            return Err(SaveError::Mismatch {
+                width: save.config.width,
+                height: save.config.height,
+                mines: save.config.mines,
+            });
+        }
 
-                    let pos = match self.get_pos_from_str(pos_str.unwrap()) {
-                        Ok(pos) => pos,
-                        Err(msg) => {
-                            self.event = Some(EventType::Error(msg));
-                            continue;
-                        }
-                    };
-                    
-                    self.flag_cell(pos);
-                },
-                "r" => {
-                    self.reveal_possible();
-                },
-                "help" | "?" | "h" => {
-                    self.display.print_help();
-                },
-                _ => {
-                    lazy_static! {
-                        // fxy is shortcut for f xy
-                        static ref FLAG_RE: Regex = Regex::new("^f[a-z][0-9]+$").unwrap();
-                    }
+        self.map.restore(save.cells, save.generated, save.guess_free, save.config.seed);
+        self.status = Status::Normal;
+        self.mines_left = save.mines_left;
+        // timer resumes on the next reveal
+        self.timer = Timer::new(save.elapsed);
+        self.undo.clear();
+        self.redo.clear();
+        self.assisted = save.assisted;
+        self.hints = save.hints;
+        self.clicks = save.clicks;
+
+        Ok(())
+    }
 
-                    let mut flag = false;
-                    let mut to_parse = input.as_str();
-                    
-                    // check flag shortcut
-                    if FLAG_RE.is_match(&input) {
-                        flag = true;
-                        to_parse = &input[1..];
-                    }
+    pub fn cells(&self) -> &Cells<'a> {
+        &self.map
+    }
 
-                    let pos = match self.get_pos_from_str(&to_parse) {
-                        Ok(pos) => pos,
-                        Err(msg) => {
-                            self.event = Some(EventType::Error(msg));
-                            continue;
-                        }
-                    };
-
-                    if flag {
-                        self.flag_cell(pos);
-                        continue;
-                    }
+    pub fn config(&self) -> &'a Config {
+        self.config
+    }
 
-                    // if there is an event to handle continue
-                    if self.guess_cell(pos, &input) {
-                        continue;
-                    }
-                }
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    pub fn mines_left(&self) -> i32 {
+        self.mines_left
+    }
+
+    // seed the board was generated from
+    pub fn seed(&self) -> u64 {
+        self.map.seed()
+    }
+
+    // no-guess board that is known to be solvable without guessing
+    pub fn guess_free(&self) -> bool {
+        self.map.guess_free()
+    }
+
+    pub fn assisted(&self) -> bool {
+        self.assisted
+    }
+
+    pub fn hints(&self) -> u32 {
+        self.hints
+    }
+
+    pub fn clicks(&self) -> u32 {
+        self.clicks
+    }
+
+    // turn undo off, for players that never take moves back
+    pub fn set_history(&mut self, history: bool) {
+        self.history = history;
+        if !history {
+            self.undo.clear();
+            self.redo.clear();
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    // share of safe cells revealed, 1 for a cleared board
+    pub fn progress(&self) -> f64 {
+        let safe = self.config.width * self.config.height - self.config.mines as usize;
+        let revealed = (0..self.config.height)
+            .flat_map(|y| (0..self.config.width).map(move |x| Position::new(x, y)))
+            .map(|pos| self.map.idx(pos))
+            .filter(|cell| !cell.hidden && cell.ctype != CellType::Mine)
+            .count();
+
+        revealed as f64 / safe as f64
+    }
+
+    // time spent playing. stands still before the first reveal and after the end
+    pub fn elapsed(&self) -> Duration {
+        self.timer.elapsed()
+    }
+
+    pub fn apply(&mut self, action: Action) -> Result<Outcome, MoveError> {
+        let outcome = match action {
+            Action::Reveal(pos) => self.reveal(pos),
+            Action::Flag(pos) => self.toggle_flag(pos),
+            Action::Chord(pos) => self.chord(pos),
+            Action::RevealPossible => self.reveal_possible(),
+            Action::Undo => return self.undo(),
+            Action::Redo => return self.redo(),
+        }?;
+
+        if !outcome.changed.is_empty() {
+            self.clicks += 1;
+        }
+        Ok(outcome)
+    }
+
+    // take back the last move, also a losing or winning one.
+    // marks the game as assisted
+    pub fn undo(&mut self) -> Result<Outcome, MoveError> {
+        let mut snapshot = self.undo.pop().ok_or(MoveError::NothingToUndo)?;
+
+        // mines stay where the first reveal put them, so the board
+        // can't change and replays keep matching their recorded layout
+        if !snapshot.map.generated() && self.map.generated() {
+            snapshot.map = self.with_layout(&snapshot.map);
+        }
+        let current = self.swap_state(snapshot);
+        self.redo.push(current);
+        self.assisted = true;
+
+        Ok(Outcome { status: self.status, changed: self.changed_since(&self.redo.last().unwrap().map) })
+    }
+
+    pub fn redo(&mut self) -> Result<Outcome, MoveError> {
+        let snapshot = self.redo.pop().ok_or(MoveError::NothingToRedo)?;
+        let current = self.swap_state(snapshot);
+        self.undo.push(current);
+
+        Ok(Outcome { status: self.status, changed: self.changed_since(&self.undo.last().unwrap().map) })
+    }
+
+    // cells marked as on the given board, mines as on the current one
+    fn with_layout(&self, marks: &Cells<'a>) -> Cells<'a> {
+        let data = (0..self.config.height)
+            .flat_map(|y| (0..self.config.width).map(move |x| Position::new(x, y)))
+            .map(|pos| Cell { ctype: self.map.idx(pos).ctype, ..marks.idx(pos) })
+            .collect();
+
+        let mut map = self.map.clone();
+        map.restore(data, true, self.map.guess_free(), self.map.seed());
+        map
+    }
+
+    // replace state with snapshot, returns the old state
+    fn swap_state(&mut self, snapshot: Snapshot<'a>) -> Snapshot<'a> {
+        let old = Snapshot {
+            map: std::mem::replace(&mut self.map, snapshot.map),
+            status: self.status,
+            mines_left: self.mines_left,
+        };
+
+        self.status = snapshot.status;
+        self.mines_left = snapshot.mines_left;
+
+        if self.status == Status::Normal {
+            if self.map.generated() {
+                self.timer.start();
             }
+        } else {
+            self.timer.stop();
         }
+
+        old
     }
-    
-    // guess cell
-    // returns:
-    //  - true - if there is event to process
-    //  - false - otherwise
-    fn guess_cell(&mut self, pos: Position, input: &str) -> bool {
-        if self.map.idx(pos).flag {
-            self.event = Some(EventType::Error(format!("there is a flag on {}", &input)));
-            return true;
+
+    // one safe cell or mine the visible board proves, or the least risky
+    // guess. counted in hints
+    pub fn hint(&mut self) -> Result<Option<Hint>, MoveError> {
+        self.check_running()?;
+
+        // where to start isn't help with the board
+        let hint = solver::hint(&self.map);
+        if matches!(hint, Some(Hint::Certain { .. } | Hint::Guess { .. })) {
+            self.hints += 1;
         }
 
-        match self.map.reveal(pos) {
-            RevealResult::Normal => {},
-            RevealResult::Mine => {
-                self.map.reveal_all();
-                self.event = Some(EventType::GameOver);
-                return true;
-            },
-            RevealResult::Win => {
-                self.map.reveal_all();
-                self.event = Some(EventType::Win);
-                return true;
+        Ok(hint)
+    }
+
+    // chance of a mine for every hidden cell, trusting the flags. exact
+    // unless the board has too many layouts to count.
+    // None when the flags don't fit the numbers
+    pub fn probabilities(&self) -> Option<Probabilities> {
+        probability::mine_probabilities(&self.map, self.mines_left)
+    }
+
+    // reveal hidden cell. revealing a revealed number chords it
+    // unless implicit chording is turned off
+    pub fn reveal(&mut self, pos: Position) -> Result<Outcome, MoveError> {
+        self.check_running()?;
+
+        let cell = self.map.idx(pos);
+
+        if cell.flag {
+            return Err(MoveError::Flagged(pos));
+        }
+
+        if !cell.hidden {
+            if !self.config.implicit_chord {
+                return Err(MoveError::NotHidden(pos));
             }
+            return self.chord(pos);
+        }
+
+        self.timer.start();
+
+        let before = self.map.clone();
+        let result = self.map.reveal(pos);
+
+        Ok(self.finish(before, result))
+    }
+
+    // reveal neighbours of a revealed number whose mines are all flagged
+    pub fn chord(&mut self, pos: Position) -> Result<Outcome, MoveError> {
+        self.check_running()?;
+
+        let cell = self.map.idx(pos);
+
+        if cell.hidden {
+            return Err(MoveError::Hidden(pos));
         }
 
-        false
+        let number = match cell.ctype {
+            CellType::Number(n) => n,
+            _ => return Err(MoveError::NotANumber(pos)),
+        };
+
+        let flags = self.map.flags_around(pos);
+        if flags != number {
+            return Err(MoveError::FlagCountMismatch { pos, number, flags });
+        }
+
+        self.timer.start();
+
+        let before = self.map.clone();
+        let result = self.map.reveal_visible(pos);
+
+        Ok(self.finish(before, result))
     }
 
-    fn reveal_possible(&mut self) {
-        let mut to_reveal = Vec::new();
-        for x in 0..self.config.width {
-            for y in 0..self.config.height {
+    // chord every revealed number. leaves the game as it is when
+    // that reveals nothing, it isn't a move
+    pub fn reveal_possible(&mut self) -> Result<Outcome, MoveError> {
+        self.check_running()?;
+
+        if !self.map.generated() {
+            return Ok(Outcome { status: self.status, changed: Vec::new() });
+        }
+
+        let before = self.map.clone();
+        let mut result = RevealResult::Normal;
+
+        'board: for y in 0..self.config.height {
+            for x in 0..self.config.width {
                 let pos = Position::new(x, y);
                 let cell = self.map.idx(pos);
 
@@ -184,57 +392,144 @@ impl<'a> Game<'a> {
                 }
 
                 if let CellType::Number(_) = cell.ctype {
-                    to_reveal.push(pos);
+                    result = self.map.reveal_visible(pos);
+
+                    if !matches!(result, RevealResult::Normal) {
+                        break 'board;
+                    }
                 }
             }
         }
 
-        for pos in to_reveal {
-            // no need for input because cell is never a flag
-            if self.guess_cell(pos, "") {
-                break;
-            }
+        if matches!(result, RevealResult::Normal) && self.changed_since(&before).is_empty() {
+            return Ok(Outcome { status: self.status, changed: Vec::new() });
         }
+
+        self.timer.start();
+        Ok(self.finish(before, result))
     }
 
-    fn flag_cell(&mut self, pos: Position) {
+    pub fn toggle_flag(&mut self, pos: Position) -> Result<Outcome, MoveError> {
+        self.check_running()?;
+
         if !self.map.idx(pos).hidden {
-            self.event = Some(EventType::Error("cell is not hidden".to_string()));
-            return;
+            return Err(MoveError::NotHidden(pos));
         }
 
+        self.push_undo(self.map.clone());
+
+        let flag = self.map.idx(pos).flag;
         self.map.flag_cell(pos);
 
-        if self.map.idx(pos).flag {
-            self.mines_left -= 1;
-        } else {
-            self.mines_left += 1;
+        // question marks don't count as mines
+        match (flag, self.map.idx(pos).flag) {
+            (false, true) => self.mines_left -= 1,
+            (true, false) => self.mines_left += 1,
+            _ => {},
         }
+
+        Ok(Outcome { status: self.status, changed: vec![pos] })
     }
 
-    // get xy from input. A10 -> x=0 y=10
-    fn get_pos_from_str(&self, input: &str) -> Result<Position, String> {
-        lazy_static! {
-            static ref RE: Regex = Regex::new("^[a-z][0-9]+$").unwrap();
+    fn check_running(&self) -> Result<(), MoveError> {
+        if self.status != Status::Normal {
+            return Err(MoveError::GameOver);
+        }
+        Ok(())
+    }
+
+    // moves are only made while the game runs and reveals don't change
+    // mines_left, so the board is all there is to remember
+    fn push_undo(&mut self, before: Cells<'a>) {
+        if !self.history {
+            return;
+        }
+
+        let limit = (MAX_UNDO_CELLS / (self.config.width * self.config.height)).max(1);
+        if self.undo.len() == limit {
+            self.undo.remove(0);
+        }
+
+        self.undo.push(Snapshot { map: before, status: Status::Normal, mines_left: self.mines_left });
+        self.redo.clear();
+    }
+
+    fn finish(&mut self, before: Cells<'a>, result: RevealResult) -> Outcome {
+        match result {
+            RevealResult::Normal => {},
+            RevealResult::Mine => {
+                self.map.reveal_all();
+                self.status = Status::Loss;
+                self.timer.stop();
+            },
+            RevealResult::Win => {
+                self.map.reveal_all();
+                self.status = Status::Win;
+                self.timer.stop();
+            }
         }
 
-        // validate coordinates
-        if !RE.is_match(&input) {
-            return Err("bad coordinates".to_string());
+        let changed = self.changed_since(&before);
+        self.push_undo(before);
+
+        Outcome { status: self.status, changed }
+    }
+
+    // cells that look different. placing the mines on the first reveal
+    // changes every hidden cell, but that can't be seen
+    fn changed_since(&self, before: &Cells) -> Vec<Position> {
+        let mut changed = Vec::new();
+        for y in 0..self.config.height {
+            for x in 0..self.config.width {
+                let pos = Position::new(x, y);
+                let (now, then) = (self.map.idx(pos), before.idx(pos));
+                if (now.hidden, now.flag, now.question) != (then.hidden, then.flag, then.question) {
+                    changed.push(pos);
+                }
+            }
         }
+        changed
+    }
 
-        let x = input.chars().next().unwrap();
-        let x = LETTERS.to_lowercase().chars().position(|e| e == x).unwrap();
-        let y = input[1..].parse().unwrap();
+    // get xy from input. A10 -> x=0 y=10, AB3 -> x=27 y=3
+    pub fn get_pos_from_str(&self, input: &str) -> Result<Position, ParseError> {
+        let pos: Position = input.parse()?;
 
-        if x > self.config.width - 1 {
-            return Err("x is too big".to_string());
+        if pos.x > self.config.width - 1 {
+            return Err(ParseError::ColumnOutOfRange { max: self.config.width - 1, got: pos.x });
         }
-        if y > self.config.height - 1 {
-            return Err("y is too big".to_string());
+        if pos.y > self.config.height - 1 {
+            return Err(ParseError::RowOutOfRange { max: self.config.height - 1, got: pos.y });
         }
 
-        Ok(Position::new(x, y))
+        Ok(pos)
     }
-}
 
+    // move typed by a player: <pos>, reveal <pos>, f/flag <pos>, f<pos>,
+    // c/chord <pos>, r/possible, u/undo or redo. positions are checked against the board
+    pub fn parse_action(&self, input: &str) -> Result<Action, ParseError> {
+        lazy_static! {
+            // fxy is shortcut for f xy
+            static ref FLAG_RE: Regex = Regex::new("^f[a-z]+[0-9]+$").unwrap();
+        }
+
+        let mut args = input.split_whitespace();
+        let name = args.next().ok_or(ParseError::Empty)?;
+        let pos = |arg: Option<&str>| self.get_pos_from_str(arg.ok_or(ParseError::MissingCoordinates)?);
+
+        match name {
+            "r" | "possible" => Ok(Action::RevealPossible),
+            "u" | "undo" => Ok(Action::Undo),
+            "redo" => Ok(Action::Redo),
+            "reveal" => Ok(Action::Reveal(pos(args.next())?)),
+            "f" | "flag" => Ok(Action::Flag(pos(args.next())?)),
+            "c" | "chord" => Ok(Action::Chord(pos(args.next())?)),
+            // on wide boards fa1 can also be a column, a valid position wins over the shortcut
+            _ => match self.get_pos_from_str(name) {
+                Ok(pos) => Ok(Action::Reveal(pos)),
+                Err(_) if FLAG_RE.is_match(name) => Ok(Action::Flag(self.get_pos_from_str(&name[1..])?)),
+                Err(err) => Err(err),
+            },
+        }
+    }
+}
@@ -1,4 +1,11 @@
-#[derive(Debug, Copy, Clone)]
+use std::{fmt, str::FromStr};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::error::ParseError;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Position {
     pub x: usize,
     pub y: usize,
@@ -11,4 +18,90 @@ impl Position {
             y
         }
     }
+
+    // positions of all cells around self that are inside a width x height board
+    pub fn neighbours(&self, width: usize, height: usize) -> Vec<Position> {
+        let mut result = Vec::with_capacity(8);
+
+        for dy in -1i64..=1 {
+            for dx in -1i64..=1 {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+
+                let x = self.x as i64 + dx;
+                let y = self.y as i64 + dy;
+
+                if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+                    continue;
+                }
+
+                result.push(Position::new(x as usize, y as usize));
+            }
+        }
+
+        result
+    }
+}
+
+// A10 for x=0 y=10, AA3 for x=26 y=3
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", column_label(self.x), self.y)
+    }
+}
+
+// a10 -> x=0 y=10, AB3 -> x=27 y=3. board size is not checked
+impl FromStr for Position {
+    type Err = ParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        lazy_static! {
+            static ref RE: Regex = Regex::new("^([a-zA-Z]+)([0-9]+)$").unwrap();
+        }
+
+        // validate coordinates
+        let caps = RE.captures(input)
+            .ok_or_else(|| ParseError::BadCoordinates(input.to_string()))?;
+
+        let x = column_from_label(&caps[1])
+            .ok_or_else(|| ParseError::BadCoordinates(input.to_string()))?;
+        let y = caps[2].parse()
+            .map_err(|_| ParseError::BadCoordinates(input.to_string()))?;
+
+        Ok(Position::new(x, y))
+    }
+}
+
+// spreadsheet style column name: A..Z, AA, AB, ..
+pub fn column_label(x: usize) -> String {
+    let mut label = Vec::new();
+    let mut x = x;
+
+    loop {
+        label.push(b'A' + (x % 26) as u8);
+        if x < 26 {
+            break;
+        }
+        x = x / 26 - 1;
+    }
+
+    label.reverse();
+    String::from_utf8(label).unwrap()
+}
+
+// inverse of column_label, case insensitive
+pub fn column_from_label(label: &str) -> Option<usize> {
+    let mut x: usize = 0;
+
+    for c in label.chars() {
+        if !c.is_ascii_alphabetic() {
+            return None;
+        }
+
+        let digit = c.to_ascii_uppercase() as usize - 'A' as usize + 1;
+        x = x.checked_mul(26)?.checked_add(digit)?;
+    }
+
+    x.checked_sub(1)
 }
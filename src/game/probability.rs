@@ -0,0 +1,535 @@
+/*
+Copyright 2022 rev1e
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+   http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use super::{cell::{Cells, CellType}, position::Position};
+
+// layouts tried per call before the rest of the frontier is estimated.
+// a few hundred milliseconds at most, expert boards stay below it
+const MAX_STEPS: usize = 10_000_000;
+
+// chance of a mine for every hidden cell that isn't flagged
+pub struct Probabilities {
+    width: usize,
+    cells: Vec<Option<f64>>,
+    exact: bool,
+}
+
+impl Probabilities {
+    // None for revealed and flagged cells
+    pub fn get(&self, pos: Position) -> Option<f64> {
+        self.cells[pos.y * self.width + pos.x]
+    }
+
+    // false when part of the frontier was too big to enumerate
+    pub fn exact(&self) -> bool {
+        self.exact
+    }
+}
+
+// revealed number with its unknown neighbours as frontier indexes
+struct Constraint {
+    cells: Vec<usize>,
+    mines: u32,
+}
+
+// connected part of the frontier and its layouts, by number of mines
+struct Component {
+    cells: Vec<usize>,
+    // totals[k]: layouts with k mines
+    totals: Vec<f64>,
+    // counts[k][i]: layouts with k mines where cells[i] is a mine
+    counts: Vec<Vec<f64>>,
+    // chances per cell when the component was too big to enumerate
+    estimate: Option<Vec<f64>>,
+}
+
+// exact probabilities from the visible board. flags are taken as mines and
+// mines_left mines are spread over the other hidden cells. frontier layouts
+// are enumerated per component and weighted by the ways to place the rest
+// of the mines in the cells no number touches. components left over when
+// MAX_STEPS runs out get a rough estimate from their numbers instead.
+// None when no layout fits the board, e.g. because of a wrong flag
+pub fn mine_probabilities(map: &Cells, mines_left: i32) -> Option<Probabilities> {
+    let config = map.config();
+    let (width, height) = (config.width, config.height);
+
+    if mines_left < 0 {
+        return None;
+    }
+    let mines_left = mines_left as usize;
+
+    let mut frontier: Vec<Position> = Vec::new();
+    let mut index: Vec<Option<usize>> = vec![None; width * height];
+    let mut constraints = Vec::new();
+
+    for y in 0..height {
+        for x in 0..width {
+            let pos = Position::new(x, y);
+            let cell = map.idx(pos);
+
+            if cell.hidden {
+                continue;
+            }
+
+            let n = match cell.ctype {
+                CellType::Number(n) => n,
+                CellType::Empty => 0,
+                CellType::Mine => continue,
+            };
+
+            let mut cells = Vec::new();
+            let mut flags = 0;
+
+            for p in pos.neighbours(width, height) {
+                let c = map.idx(p);
+                if !c.hidden {
+                    continue;
+                }
+                if c.flag {
+                    flags += 1;
+                    continue;
+                }
+
+                let i = match index[p.y * width + p.x] {
+                    Some(i) => i,
+                    None => {
+                        frontier.push(p);
+                        index[p.y * width + p.x] = Some(frontier.len() - 1);
+                        frontier.len() - 1
+                    }
+                };
+                cells.push(i);
+            }
+
+            if flags > n || (n - flags) as usize > cells.len() {
+                return None;
+            }
+
+            if !cells.is_empty() {
+                constraints.push(Constraint { cells, mines: n - flags });
+            }
+        }
+    }
+
+    let interior = (0..width * height)
+        .filter(|&i| index[i].is_none())
+        .filter(|&i| {
+            let cell = map.idx(Position::new(i % width, i / width));
+            cell.hidden && !cell.flag
+        })
+        .count();
+
+    // constraints every frontier cell is part of
+    let mut of_cell = vec![Vec::new(); frontier.len()];
+    for (c, constraint) in constraints.iter().enumerate() {
+        for &i in &constraint.cells {
+            of_cell[i].push(c);
+        }
+    }
+
+    let mut search = Search {
+        constraints: &constraints,
+        of_cell: &of_cell,
+        have: vec![0; constraints.len()],
+        left: constraints.iter().map(|c| c.cells.len() as u32).collect(),
+        mine: vec![false; frontier.len()],
+        max_mines: mines_left,
+        steps: 0,
+    };
+
+    // small components first, so one huge one doesn't use up all steps
+    let mut orders = components_of(&constraints, &of_cell);
+    orders.sort_by_key(|order| order.len());
+
+    let mut components = Vec::new();
+    for order in orders {
+        let mut component = Component {
+            cells: Vec::new(),
+            totals: vec![0.0; order.len() + 1],
+            counts: vec![vec![0.0; order.len()]; order.len() + 1],
+            estimate: None,
+        };
+        search.enumerate(&order, 0, 0, &mut component);
+
+        if search.steps >= MAX_STEPS {
+            component = estimate(&order, &constraints, &of_cell);
+        }
+
+        component.cells = order;
+        components.push(component);
+    }
+
+    // weight of placing the other mines in the interior, scaled so the
+    // largest one is 1
+    let ln_weights: Vec<f64> = (0..=frontier.len())
+        .map(|m| if m > mines_left { f64::NEG_INFINITY } else { ln_choose(interior, mines_left - m) })
+        .collect();
+    let max = ln_weights.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if max == f64::NEG_INFINITY {
+        return None;
+    }
+    let weight = |m: usize| ln_weights.get(m).map_or(0.0, |w| (w - max).exp());
+
+    let all = components.iter().fold(vec![1.0], |acc, c| convolve(&acc, &c.totals));
+    let total: f64 = all.iter().enumerate().map(|(m, w)| w * weight(m)).sum();
+    if total == 0.0 {
+        return None;
+    }
+
+    let mut cells = vec![None; width * height];
+
+    for (j, component) in components.iter().enumerate() {
+        if let Some(estimate) = &component.estimate {
+            for (i, &cell) in component.cells.iter().enumerate() {
+                let pos = frontier[cell];
+                cells[pos.y * width + pos.x] = Some(estimate[i]);
+            }
+            continue;
+        }
+
+        // layouts of all other components
+        let others = components.iter()
+            .enumerate()
+            .filter(|&(i, _)| i != j)
+            .fold(vec![1.0], |acc, (_, c)| convolve(&acc, &c.totals));
+
+        for (i, &cell) in component.cells.iter().enumerate() {
+            let mut p = 0.0;
+            for (k, counts) in component.counts.iter().enumerate() {
+                if counts[i] == 0.0 {
+                    continue;
+                }
+                let rest: f64 = others.iter().enumerate().map(|(m, w)| w * weight(k + m)).sum();
+                p += counts[i] * rest;
+            }
+
+            let pos = frontier[cell];
+            cells[pos.y * width + pos.x] = Some(p / total);
+        }
+    }
+
+    if interior > 0 {
+        let p = all.iter()
+            .enumerate()
+            .filter(|&(m, _)| m <= mines_left)
+            .map(|(m, w)| w * weight(m) * (mines_left - m) as f64 / interior as f64)
+            .sum::<f64>() / total;
+
+        for (i, cell) in cells.iter_mut().enumerate() {
+            let c = map.idx(Position::new(i % width, i / width));
+            if index[i].is_none() && c.hidden && !c.flag {
+                *cell = Some(p);
+            }
+        }
+    }
+
+    let exact = components.iter().all(|c| c.estimate.is_none());
+    Some(Probabilities { width, cells, exact })
+}
+
+// worst share of mines among the numbers around each cell. the number
+// of mines in the component is left open, any count fits
+fn estimate(order: &[usize], constraints: &[Constraint], of_cell: &[Vec<usize>]) -> Component {
+    let estimate = order.iter()
+        .map(|&cell| of_cell[cell].iter()
+            .map(|&c| constraints[c].mines as f64 / constraints[c].cells.len() as f64)
+            .fold(0.0, f64::max))
+        .collect();
+
+    Component { cells: Vec::new(), totals: vec![1.0; order.len() + 1], counts: Vec::new(), estimate: Some(estimate) }
+}
+
+// backtracking over the cells of one component
+struct Search<'c> {
+    constraints: &'c [Constraint],
+    of_cell: &'c [Vec<usize>],
+    // mines placed and cells not decided yet around every constraint
+    have: Vec<u32>,
+    left: Vec<u32>,
+    mine: Vec<bool>,
+    max_mines: usize,
+    // calls so far, the search gives up at MAX_STEPS
+    steps: usize,
+}
+
+impl<'c> Search<'c> {
+    fn enumerate(&mut self, order: &[usize], depth: usize, mines: usize, component: &mut Component) {
+        if mines > self.max_mines || self.steps >= MAX_STEPS {
+            return;
+        }
+        self.steps += 1;
+
+        if depth == order.len() {
+            component.totals[mines] += 1.0;
+            for (i, &cell) in order.iter().enumerate() {
+                if self.mine[cell] {
+                    component.counts[mines][i] += 1.0;
+                }
+            }
+            return;
+        }
+
+        let cell = order[depth];
+        for mine in [false, true] {
+            if self.assign(cell, mine) {
+                self.mine[cell] = mine;
+                self.enumerate(order, depth + 1, mines + mine as usize, component);
+            }
+            self.unassign(cell, mine);
+        }
+        self.mine[cell] = false;
+    }
+
+    // returns false if a constraint can't be met anymore
+    fn assign(&mut self, cell: usize, mine: bool) -> bool {
+        let of_cell = self.of_cell;
+        let mut ok = true;
+
+        for &c in &of_cell[cell] {
+            self.left[c] -= 1;
+            if mine {
+                self.have[c] += 1;
+            }
+
+            let need = self.constraints[c].mines;
+            if self.have[c] > need || self.have[c] + self.left[c] < need {
+                ok = false;
+            }
+        }
+
+        ok
+    }
+
+    fn unassign(&mut self, cell: usize, mine: bool) {
+        let of_cell = self.of_cell;
+
+        for &c in &of_cell[cell] {
+            self.left[c] += 1;
+            if mine {
+                self.have[c] -= 1;
+            }
+        }
+    }
+}
+
+// frontier cells grouped by shared constraints. cells come in breadth first
+// order so constraints are completed early during the search
+fn components_of(constraints: &[Constraint], of_cell: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut seen = vec![false; of_cell.len()];
+    let mut result = Vec::new();
+
+    for start in 0..of_cell.len() {
+        if seen[start] {
+            continue;
+        }
+
+        seen[start] = true;
+        let mut order = vec![start];
+        let mut i = 0;
+
+        while i < order.len() {
+            for &c in &of_cell[order[i]] {
+                for &next in &constraints[c].cells {
+                    if !seen[next] {
+                        seen[next] = true;
+                        order.push(next);
+                    }
+                }
+            }
+            i += 1;
+        }
+
+        result.push(order);
+    }
+
+    result
+}
+
+fn convolve(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let mut result = vec![0.0; a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            result[i + j] += x * y;
+        }
+    }
+    result
+}
+
+// ln of n choose k, minus infinity when k > n
+fn ln_choose(n: usize, k: usize) -> f64 {
+    if k > n {
+        return f64::NEG_INFINITY;
+    }
+    let k = k.min(n - k);
+    (1..=k).map(|i| ((n - k + i) as f64 / i as f64).ln()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{config::Config, game::cell::Cell};
+
+    use super::*;
+
+    // chance of a mine per cell by trying every layout of the hidden cells
+    fn brute_force(map: &Cells) -> Vec<Option<f64>> {
+        let config = map.config();
+        let positions: Vec<Position> = (0..config.height)
+            .flat_map(|y| (0..config.width).map(move |x| Position::new(x, y)))
+            .collect();
+        let hidden: Vec<usize> = (0..positions.len()).filter(|&i| map.idx(positions[i]).hidden).collect();
+
+        let mut layouts = 0.0;
+        let mut counts = vec![0.0; positions.len()];
+
+        for mask in 0u32..1 << hidden.len() {
+            if mask.count_ones() != config.mines {
+                continue;
+            }
+
+            let mut mine = vec![false; positions.len()];
+            for (bit, &i) in hidden.iter().enumerate() {
+                mine[i] = mask & 1 << bit != 0;
+            }
+
+            let fits = positions.iter().filter(|&&pos| !map.idx(pos).hidden).all(|&pos| {
+                let n = match map.idx(pos).ctype {
+                    CellType::Number(n) => n,
+                    _ => 0,
+                };
+                let around = pos.neighbours(config.width, config.height).iter()
+                    .filter(|p| mine[p.y * config.width + p.x])
+                    .count();
+                around as u32 == n
+            });
+
+            if fits {
+                layouts += 1.0;
+                for &i in &hidden {
+                    if mine[i] {
+                        counts[i] += 1.0;
+                    }
+                }
+            }
+        }
+
+        (0..positions.len())
+            .map(|i| if hidden.contains(&i) { Some(counts[i] / layouts) } else { None })
+            .collect()
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        for seed in 0..40 {
+            let config = Config {
+                width: 6,
+                height: 4,
+                mines: 5,
+                seed,
+                no_guess: false,
+                implicit_chord: true,
+                question_marks: false,
+            };
+            let mut map = Cells::new(&config);
+            map.reveal(Position::new(seed as usize % 6, seed as usize % 4));
+
+            // a few more safe cells for numbers on more than one side
+            for i in 0..3 {
+                let safe = (0..24)
+                    .map(|j| Position::new(j % 6, j / 6))
+                    .filter(|&p| map.idx(p).hidden && map.idx(p).ctype != CellType::Mine)
+                    .nth(i * 2);
+
+                if let Some(pos) = safe {
+                    map.reveal(pos);
+                }
+            }
+
+            let probabilities = mine_probabilities(&map, config.mines as i32).unwrap();
+            let expected = brute_force(&map);
+
+            for (i, expected) in expected.into_iter().enumerate() {
+                let got = probabilities.get(Position::new(i % 6, i / 6));
+                match (got, expected) {
+                    (Some(got), Some(expected)) => assert!((got - expected).abs() < 1e-9,
+                        "seed {} cell {}: {} != {}", seed, i, got, expected),
+                    (got, expected) => assert_eq!(got.is_some(), expected.is_some(), "seed {} cell {}", seed, i),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn large_board() {
+        let config = Config {
+            width: 60,
+            height: 60,
+            mines: 720,
+            seed: 1,
+            no_guess: false,
+            implicit_chord: true,
+            question_marks: false,
+        };
+        let mut map = Cells::new(&config);
+        map.reveal(Position::new(30, 30));
+
+        // scattered safe cells join up into one frontier far too big to enumerate
+        for y in 0..60 {
+            for x in 0..60 {
+                let pos = Position::new(x, y);
+                if (x + 2 * y) % 5 == 0 && map.idx(pos).ctype != CellType::Mine {
+                    map.reveal(pos);
+                }
+            }
+        }
+
+        let start = std::time::Instant::now();
+        let probabilities = mine_probabilities(&map, config.mines as i32).unwrap();
+
+        assert!(!probabilities.exact());
+        assert!(start.elapsed().as_secs() < 20, "took {:?}", start.elapsed());
+    }
+
+    #[test]
+    fn wrong_flags() {
+        let config = Config {
+            width: 6,
+            height: 2,
+            mines: 3,
+            seed: 1,
+            no_guess: false,
+            implicit_chord: true,
+            question_marks: false,
+        };
+
+        // *#..#*
+        // ......
+        let data = (0..12)
+            .map(|i| Cell {
+                hidden: i < 6 && i != 2 && i != 3,
+                flag: false,
+                question: false,
+                ctype: if i == 0 || i == 5 { CellType::Mine } else { CellType::Empty },
+            })
+            .collect();
+        let mut map = Cells::new(&config);
+        map.restore(data, true, false, 0);
+
+        // two flags on the 1 below them, the mine on the right still fits
+        map.flag_cell(Position::new(0, 0));
+        map.flag_cell(Position::new(1, 0));
+        assert!(mine_probabilities(&map, config.mines as i32 - 2).is_none());
+    }
+}
@@ -0,0 +1,656 @@
+/*
+Copyright 2022 rev1e
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+   http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::fmt;
+
+use super::{cell::{Cells, CellType}, position::Position, probability};
+
+// what the solver knows about a cell
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Knowledge {
+    Unknown,
+    Mine,
+    // deduced safe, number not known yet
+    Safe,
+    // revealed with number of mines around (0 for empty)
+    Revealed(u32),
+}
+
+// cell that is certainly a mine or certainly safe
+#[derive(Debug, Clone)]
+pub struct Deduction {
+    pub pos: Position,
+    pub mine: bool,
+    // revealed numbers that force this deduction.
+    // empty when it follows from the total number of mines
+    pub reasons: Vec<Position>,
+}
+
+// cell suggested to the player
+#[derive(Debug, Clone)]
+pub enum Hint {
+    // forced by revealed numbers, given with their positions
+    Certain { pos: Position, mine: bool, reasons: Vec<(Position, u32)> },
+    // nothing is certain, cell with the lowest chance of a mine.
+    // not exact when the board has too many layouts to count
+    Guess { pos: Position, risk: f64, exact: bool },
+    // nothing revealed yet, where to click first
+    Start { pos: Position },
+}
+
+impl Hint {
+    pub fn pos(&self) -> Position {
+        match self {
+            Hint::Certain { pos, .. } | Hint::Guess { pos, .. } | Hint::Start { pos } => *pos,
+        }
+    }
+}
+
+// B3=1 and C3=1 imply D4 is safe
+impl fmt::Display for Hint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Hint::Certain { pos, mine, reasons } => {
+                let what = if *mine { "a mine" } else { "safe" };
+
+                if reasons.is_empty() {
+                    return write!(f, "mine count implies {} is {}", pos, what);
+                }
+
+                let numbers: Vec<String> = reasons.iter().map(|(p, n)| format!("{}={}", p, n)).collect();
+                let verb = if reasons.len() == 1 { "implies" } else { "imply" };
+                write!(f, "{} {} {} is {}", numbers.join(" and "), verb, pos, what)
+            },
+            Hint::Guess { pos, risk, exact: true } => {
+                write!(f, "no certain cell, {} has the lowest risk ({:.1}%)", pos, risk * 100.0)
+            },
+            Hint::Guess { pos, risk, exact: false } => {
+                write!(f, "no certain cell, {} has the lowest estimated risk (about {:.0}%)", pos, risk * 100.0)
+            },
+            Hint::Start { pos } => {
+                write!(f, "nothing revealed yet, start at {}, the first click is never a mine", pos)
+            },
+        }
+    }
+}
+
+// number cell with its undecided neighbours
+struct Constraint {
+    pos: Position,
+    cells: [Position; 8],
+    len: usize,
+    mines: u32,
+}
+
+impl Constraint {
+    fn unknown(&self) -> &[Position] {
+        &self.cells[..self.len]
+    }
+}
+
+// deterministic solver working only on the visible board.
+// flags are not trusted, solver makes its own mine deductions.
+// numbers are only looked at again when a cell around them changes
+pub struct Solver {
+    width: usize,
+    height: usize,
+    mines: u32,
+    cells: Vec<Knowledge>,
+    mines_found: u32,
+    unknown: usize,
+    // numbers to check on the next pass
+    dirty: Vec<Position>,
+    queued: Vec<bool>,
+}
+
+impl Solver {
+    pub fn new(map: &Cells) -> Self {
+        let config = map.config();
+        let size = config.width * config.height;
+
+        let mut solver = Self {
+            width: config.width,
+            height: config.height,
+            mines: config.mines,
+            cells: vec![Knowledge::Unknown; size],
+            mines_found: 0,
+            unknown: size,
+            dirty: Vec::new(),
+            queued: vec![false; size],
+        };
+
+        solver.update(map);
+        solver
+    }
+
+    // take in cells revealed on the map since the last update
+    pub fn update(&mut self, map: &Cells) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let pos = Position::new(x, y);
+                let cell = map.idx(pos);
+
+                if cell.hidden || matches!(self.get(pos), Knowledge::Revealed(_)) {
+                    continue;
+                }
+
+                let knowledge = match cell.ctype {
+                    CellType::Number(n) => Knowledge::Revealed(n),
+                    CellType::Empty => Knowledge::Revealed(0),
+                    CellType::Mine => Knowledge::Mine,
+                };
+
+                if knowledge != self.get(pos) {
+                    self.set(pos, knowledge);
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, pos: Position) -> Knowledge {
+        self.cells[pos.y * self.width + pos.x]
+    }
+
+    fn set(&mut self, pos: Position, knowledge: Knowledge) {
+        let old = std::mem::replace(&mut self.cells[pos.y * self.width + pos.x], knowledge);
+
+        if old == Knowledge::Unknown {
+            self.unknown -= 1;
+        }
+        if old == Knowledge::Mine {
+            self.mines_found -= 1;
+        }
+        if knowledge == Knowledge::Mine {
+            self.mines_found += 1;
+        }
+
+        // the cell itself when revealed, and the numbers around it
+        for y in pos.y.saturating_sub(1)..(pos.y + 2).min(self.height) {
+            for x in pos.x.saturating_sub(1)..(pos.x + 2).min(self.width) {
+                self.queue(Position::new(x, y));
+            }
+        }
+    }
+
+    fn queue(&mut self, pos: Position) {
+        let i = pos.y * self.width + pos.x;
+
+        if !self.queued[i] && matches!(self.cells[i], Knowledge::Revealed(_)) {
+            self.queued[i] = true;
+            self.dirty.push(pos);
+        }
+    }
+
+    // deduce everything possible without revealing new cells.
+    // returns deductions in the order they were found
+    pub fn solve(&mut self) -> Vec<Deduction> {
+        let mut result = Vec::new();
+
+        loop {
+            let work: Vec<Constraint> = std::mem::take(&mut self.dirty)
+                .into_iter()
+                .filter_map(|pos| {
+                    self.queued[pos.y * self.width + pos.x] = false;
+                    self.constraint(pos)
+                })
+                .collect();
+
+            let mut found = self.single_pass(&work);
+
+            if found.is_empty() {
+                found = self.pair_pass(&work);
+            } else {
+                // pairs of these weren't checked yet
+                for c in &work {
+                    self.queue(c.pos);
+                }
+            }
+
+            if found.is_empty() {
+                found = self.count_pass();
+            }
+
+            if found.is_empty() {
+                break;
+            }
+
+            for deduction in found {
+                if self.get(deduction.pos) != Knowledge::Unknown {
+                    continue;
+                }
+
+                let knowledge = if deduction.mine { Knowledge::Mine } else { Knowledge::Safe };
+                self.set(deduction.pos, knowledge);
+                result.push(deduction);
+            }
+        }
+
+        result
+    }
+
+    fn constraint(&self, pos: Position) -> Option<Constraint> {
+        let n = match self.get(pos) {
+            Knowledge::Revealed(n) => n,
+            _ => return None,
+        };
+
+        // no allocation here, this runs for every number near a change
+        let mut cells = [pos; 8];
+        let mut len = 0;
+        let mut mines = 0;
+
+        for y in pos.y.saturating_sub(1)..(pos.y + 2).min(self.height) {
+            for x in pos.x.saturating_sub(1)..(pos.x + 2).min(self.width) {
+                let p = Position::new(x, y);
+
+                match self.get(p) {
+                    Knowledge::Unknown => {
+                        cells[len] = p;
+                        len += 1;
+                    },
+                    Knowledge::Mine => mines += 1,
+                    _ => {},
+                }
+            }
+        }
+
+        if len == 0 || mines > n {
+            return None;
+        }
+
+        Some(Constraint { pos, cells, len, mines: n - mines })
+    }
+
+    // number is already satisfied or needs all of its unknown neighbours
+    fn single_pass(&self, work: &[Constraint]) -> Vec<Deduction> {
+        let mut result = Vec::new();
+
+        for c in work {
+            if c.mines == 0 || c.mines as usize == c.unknown().len() {
+                for &pos in c.unknown() {
+                    result.push(Deduction { pos, mine: c.mines > 0, reasons: vec![c.pos] });
+                }
+            }
+        }
+
+        result
+    }
+
+    // compare two overlapping numbers: bounds on the mines in the shared cells
+    // can force the cells only the second number sees.
+    // every number in work is paired with the numbers up to 2 cells away
+    fn pair_pass(&self, work: &[Constraint]) -> Vec<Deduction> {
+        let mut result = Vec::new();
+
+        for a in work {
+            for y in a.pos.y.saturating_sub(2)..(a.pos.y + 3).min(self.height) {
+                for x in a.pos.x.saturating_sub(2)..(a.pos.x + 3).min(self.width) {
+                    let pos = Position::new(x, y);
+                    if pos == a.pos {
+                        continue;
+                    }
+
+                    if let Some(b) = self.constraint(pos) {
+                        pair(a, &b, &mut result);
+                        pair(&b, a, &mut result);
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    // numbers linked to pos through the unknown cells they share,
+    // together they decide pos
+    fn linked_numbers(&self, pos: Position) -> Vec<Position> {
+        let mut seen = vec![false; self.cells.len()];
+        let mut numbers = Vec::new();
+        let mut queue = vec![pos];
+        seen[pos.y * self.width + pos.x] = true;
+
+        while let Some(cell) = queue.pop() {
+            for p in cell.neighbours(self.width, self.height) {
+                let constraint = match self.constraint(p) {
+                    Some(c) if !seen[p.y * self.width + p.x] => c,
+                    _ => continue,
+                };
+
+                seen[p.y * self.width + p.x] = true;
+                numbers.push(p);
+
+                for &u in constraint.unknown() {
+                    if !seen[u.y * self.width + u.x] {
+                        seen[u.y * self.width + u.x] = true;
+                        queue.push(u);
+                    }
+                }
+            }
+        }
+
+        numbers.sort_by_key(|p| (p.y, p.x));
+        numbers
+    }
+
+    // all mines are found or every unknown cell has to be a mine
+    fn count_pass(&self) -> Vec<Deduction> {
+        if self.unknown == 0 || self.mines_found > self.mines {
+            return Vec::new();
+        }
+
+        let mines_left = self.mines - self.mines_found;
+
+        if mines_left != 0 && mines_left as usize != self.unknown {
+            return Vec::new();
+        }
+
+        (0..self.cells.len())
+            .filter(|&i| self.cells[i] == Knowledge::Unknown)
+            .map(|i| Deduction {
+                pos: Position::new(i % self.width, i / self.width),
+                mine: mines_left > 0,
+                reasons: Vec::new(),
+            })
+            .collect()
+    }
+}
+
+// deductions b can make knowing a
+fn pair(a: &Constraint, b: &Constraint, result: &mut Vec<Deduction>) {
+    let shared = a.unknown().iter().filter(|p| b.unknown().contains(p)).count() as u32;
+    if shared == 0 {
+        return;
+    }
+
+    let only_a = a.unknown().len() as u32 - shared;
+    let only_b: Vec<Position> = b.unknown().iter()
+        .filter(|p| !a.unknown().contains(p))
+        .copied()
+        .collect();
+
+    if only_b.is_empty() {
+        return;
+    }
+
+    let max_shared = shared.min(a.mines).min(b.mines);
+    let min_shared = a.mines.saturating_sub(only_a);
+
+    let mine = if b.mines == min_shared {
+        false
+    } else if b.mines - max_shared == only_b.len() as u32 {
+        true
+    } else {
+        return;
+    };
+
+    for &pos in &only_b {
+        result.push(Deduction { pos, mine, reasons: vec![a.pos, b.pos] });
+    }
+}
+
+// pick one cell for the player: the first safe cell or unflagged mine
+// found, so its reasons don't lean on later deductions. falls back to
+// the unflagged cell with the lowest exact chance of a mine
+pub fn hint(map: &Cells) -> Option<Hint> {
+    let config = map.config();
+
+    if !map.generated() {
+        return Some(Hint::Start { pos: Position::new(config.width / 2, config.height / 2) });
+    }
+
+    let mut solver = Solver::new(map);
+    let deductions = solver.solve();
+
+    let number = |p: &Position| match map.idx(*p).ctype {
+        CellType::Number(n) => (*p, n),
+        _ => (*p, 0),
+    };
+
+    let certain = deductions.iter().find(|d| !d.mine || !map.idx(d.pos).flag);
+
+    if let Some(d) = certain {
+        let reasons = d.reasons.iter().map(number).collect();
+        return Some(Hint::Certain { pos: d.pos, mine: d.mine, reasons });
+    }
+
+    // flags aren't trusted here either, chances are from the numbers alone
+    let mut unmarked = map.clone();
+    let positions: Vec<Position> = (0..config.height)
+        .flat_map(|y| (0..config.width).map(move |x| Position::new(x, y)))
+        .collect();
+
+    for &pos in &positions {
+        let cell = unmarked.idx_mut(pos);
+        cell.flag = false;
+        cell.question = false;
+    }
+
+    let probabilities = probability::mine_probabilities(&unmarked, config.mines as i32)?;
+
+    // flagged cells aren't offered as guesses
+    let (pos, risk) = positions.into_iter()
+        .filter(|pos| !map.idx(*pos).flag)
+        .filter_map(|pos| probabilities.get(pos).map(|risk| (pos, risk)))
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())?;
+
+    // safe in every layout, but it takes more than two numbers to see
+    if risk == 0.0 && probabilities.exact() {
+        let reasons = solver.linked_numbers(pos).iter().map(number).collect();
+        return Some(Hint::Certain { pos, mine: false, reasons });
+    }
+
+    Some(Hint::Guess { pos, risk, exact: probabilities.exact() })
+}
+
+// play the board from the first click using only deductions.
+// map must already have its mines placed
+pub fn is_solvable(map: &Cells, first: Position) -> bool {
+    let config = map.config();
+    let mut map = map.clone();
+
+    // the player's marks would stop the reveals below
+    for y in 0..config.height {
+        for x in 0..config.width {
+            let cell = map.idx_mut(Position::new(x, y));
+            cell.flag = false;
+            cell.question = false;
+        }
+    }
+
+    map.reveal(first);
+
+    let mut solver = Solver::new(&map);
+
+    loop {
+        if map.check_win() {
+            return true;
+        }
+
+        let safe: Vec<Position> = solver.solve()
+            .into_iter()
+            .filter(|d| !d.mine)
+            .map(|d| d.pos)
+            .collect();
+
+        if safe.is_empty() {
+            return false;
+        }
+
+        for pos in safe {
+            map.reveal(pos);
+        }
+
+        solver.update(&map);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{config::Config, game::cell::Cell};
+
+    use super::*;
+
+    fn config(rows: &[&str], mines: u32) -> Config {
+        Config {
+            width: rows[0].len(),
+            height: rows.len(),
+            mines,
+            seed: 0,
+            no_guess: false,
+            implicit_chord: true,
+            question_marks: false,
+        }
+    }
+
+    // rows as in save files: # hidden, * hidden mine, . revealed
+    fn board<'a>(config: &'a Config, rows: &[&str]) -> Cells<'a> {
+        let data = rows.iter()
+            .flat_map(|row| row.chars())
+            .map(|c| Cell {
+                hidden: c != '.',
+                flag: false,
+                question: false,
+                ctype: if c == '*' { CellType::Mine } else { CellType::Empty },
+            })
+            .collect();
+
+        let mut map = Cells::new(config);
+        map.restore(data, true, false, 0);
+        map
+    }
+
+    fn find(deductions: &[Deduction], pos: Position) -> &Deduction {
+        deductions.iter().find(|d| d.pos == pos).unwrap()
+    }
+
+    #[test]
+    fn single() {
+        let rows = ["*##", "..."];
+        let config = config(&rows, 1);
+        let map = board(&config, &rows);
+
+        let deductions = Solver::new(&map).solve();
+
+        // C1=0 clears its neighbours, then A1=1 has one cell left
+        let b0 = find(&deductions, Position::new(1, 0));
+        assert!(!b0.mine);
+        assert_eq!(b0.reasons, vec![Position::new(2, 1)]);
+
+        let a0 = find(&deductions, Position::new(0, 0));
+        assert!(a0.mine);
+        assert_eq!(a0.reasons, vec![Position::new(0, 1)]);
+    }
+
+    #[test]
+    fn pair() {
+        let rows = ["#*#", "..."];
+        let config = config(&rows, 1);
+        let map = board(&config, &rows);
+
+        let deductions = Solver::new(&map).solve();
+
+        // 1 1 1: the middle one's mine is one of the edge's two cells
+        let c0 = find(&deductions, Position::new(2, 0));
+        assert!(!c0.mine);
+        assert_eq!(c0.reasons, vec![Position::new(0, 1), Position::new(1, 1)]);
+
+        let a0 = find(&deductions, Position::new(0, 0));
+        assert!(!a0.mine);
+        assert_eq!(a0.reasons, vec![Position::new(2, 1), Position::new(1, 1)]);
+
+        assert!(find(&deductions, Position::new(1, 0)).mine);
+    }
+
+    #[test]
+    fn count() {
+        let rows = [".*#", ".*#"];
+        let config = config(&rows, 2);
+        let map = board(&config, &rows);
+
+        let deductions = Solver::new(&map).solve();
+
+        // no number touches column C, but both mines are already found
+        for pos in [Position::new(2, 0), Position::new(2, 1)] {
+            let d = find(&deductions, pos);
+            assert!(!d.mine);
+            assert!(d.reasons.is_empty());
+        }
+    }
+
+    #[test]
+    fn no_deduction() {
+        let rows = ["*#", ".."];
+        let config = config(&rows, 1);
+        let map = board(&config, &rows);
+
+        assert!(Solver::new(&map).solve().is_empty());
+    }
+
+    #[test]
+    fn flag_before_first_click() {
+        for seed in 0..20 {
+            let config = Config {
+                width: 9,
+                height: 9,
+                mines: 10,
+                seed,
+                no_guess: true,
+                implicit_chord: true,
+                question_marks: false,
+            };
+
+            let mut plain = Cells::new(&config);
+            plain.reveal(Position::new(4, 4));
+
+            let mut flagged = Cells::new(&config);
+            flagged.flag_cell(Position::new(0, 0));
+            flagged.reveal(Position::new(4, 4));
+
+            for y in 0..9 {
+                for x in 0..9 {
+                    let pos = Position::new(x, y);
+                    assert_eq!(plain.idx(pos).ctype, flagged.idx(pos).ctype, "seed {} at {}", seed, pos);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hint_before_first_click() {
+        let rows = ["###", "###"];
+        let config = config(&rows, 1);
+        let map = Cells::new(&config);
+
+        assert!(matches!(hint(&map), Some(Hint::Start { .. })));
+    }
+
+    #[test]
+    fn update_after_reveal() {
+        let rows = ["*##", "#..", "###"];
+        let config = config(&rows, 1);
+        let mut map = board(&config, &rows);
+        let mut solver = Solver::new(&map);
+
+        // B1=1 can't tell which of A0, A1 and A2 is the mine
+        assert!(solver.solve().iter().all(|d| d.pos != Position::new(0, 0)));
+
+        // A2=0 clears A1, leaving A0 for B1
+        map.reveal(Position::new(0, 2));
+        solver.update(&map);
+
+        assert!(solver.solve().iter().any(|d| d.pos == Position::new(0, 0) && d.mine));
+    }
+}
@@ -0,0 +1,147 @@
+/*
+Copyright 2022 rev1e
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+   http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::{fmt, time::Duration};
+
+use super::{Game, Status, cell::{Cells, CellType}, position::Position};
+
+// standard metrics of a finished board
+#[derive(Debug, Clone)]
+pub struct BoardStats {
+    // bechtel's board benchmark value: fewest clicks that clear the board
+    // without flags or chording. one per opening and one per number that
+    // doesn't border an opening
+    pub three_bv: u32,
+    // connected areas of empty cells, cleared with one click each
+    pub openings: u32,
+    // connected groups of numbers that don't border an opening
+    pub islands: u32,
+    pub clicks: u32,
+    pub time: Duration,
+    pub won: bool,
+}
+
+impl BoardStats {
+    // None before the mines are placed
+    pub fn new(game: &Game) -> Option<Self> {
+        let map = game.cells();
+        if !map.generated() {
+            return None;
+        }
+
+        let (three_bv, openings, islands) = layout(map);
+
+        Some(Self {
+            three_bv,
+            openings,
+            islands,
+            clicks: game.clicks(),
+            time: game.elapsed(),
+            won: game.status() == Status::Win,
+        })
+    }
+
+    // 3BV per click, only for a cleared board. above 1 with flags
+    // and chords used well
+    pub fn efficiency(&self) -> Option<f64> {
+        (self.won && self.clicks > 0).then(|| self.three_bv as f64 / self.clicks as f64)
+    }
+
+    pub fn three_bv_per_second(&self) -> Option<f64> {
+        (self.won && !self.time.is_zero()).then(|| self.three_bv as f64 / self.time.as_secs_f64())
+    }
+}
+
+// 3BV: 45, openings: 3, islands: 8
+// clicks: 52, efficiency: 87%, 3BV/s: 1.23
+impl fmt::Display for BoardStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "3BV: {}, openings: {}, islands: {}", self.three_bv, self.openings, self.islands)?;
+        write!(f, "clicks: {}", self.clicks)?;
+
+        if let Some(efficiency) = self.efficiency() {
+            write!(f, ", efficiency: {:.0}%", efficiency * 100.0)?;
+        }
+        if let Some(rate) = self.three_bv_per_second() {
+            write!(f, ", 3BV/s: {:.2}", rate)?;
+        }
+
+        Ok(())
+    }
+}
+
+// 3BV, openings and islands of a layout
+fn layout(map: &Cells) -> (u32, u32, u32) {
+    let config = map.config();
+    let (width, height) = (config.width, config.height);
+    let at = |i: usize| map.idx(Position::new(i % width, i / width)).ctype;
+
+    // flood every opening and the numbers around it
+    let mut cleared = vec![false; width * height];
+    let mut openings = 0;
+
+    for i in 0..cleared.len() {
+        if cleared[i] || at(i) != CellType::Empty {
+            continue;
+        }
+
+        openings += 1;
+        cleared[i] = true;
+        let mut stack = vec![i];
+
+        while let Some(i) = stack.pop() {
+            if at(i) != CellType::Empty {
+                continue;
+            }
+
+            for p in Position::new(i % width, i / width).neighbours(width, height) {
+                let j = p.y * width + p.x;
+                if !cleared[j] {
+                    cleared[j] = true;
+                    stack.push(j);
+                }
+            }
+        }
+    }
+
+    // numbers left over need a click each, grouped into islands
+    let mut numbers = 0;
+    let mut islands = 0;
+
+    for i in 0..cleared.len() {
+        if cleared[i] || !matches!(at(i), CellType::Number(_)) {
+            continue;
+        }
+
+        islands += 1;
+        cleared[i] = true;
+        let mut stack = vec![i];
+
+        while let Some(i) = stack.pop() {
+            numbers += 1;
+
+            for p in Position::new(i % width, i / width).neighbours(width, height) {
+                let j = p.y * width + p.x;
+                if !cleared[j] && matches!(at(j), CellType::Number(_)) {
+                    cleared[j] = true;
+                    stack.push(j);
+                }
+            }
+        }
+    }
+
+    (openings + numbers, openings, islands)
+}
@@ -0,0 +1,57 @@
+/*
+Copyright 2022 rev1e
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+   http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::time::{Duration, Instant};
+
+// stopwatch that can be paused and resumed
+#[derive(Debug, Clone)]
+pub struct Timer {
+    // time measured before the last start
+    offset: Duration,
+    started: Option<Instant>,
+}
+
+impl Timer {
+    // stopped timer that already measured elapsed
+    pub fn new(elapsed: Duration) -> Self {
+        Self {
+            offset: elapsed,
+            started: None,
+        }
+    }
+
+    pub fn start(&mut self) {
+        if self.started.is_none() {
+            self.started = Some(Instant::now());
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.offset = self.elapsed();
+        self.started = None;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        match self.started {
+            Some(started) => self.offset + started.elapsed(),
+            None => self.offset,
+        }
+    }
+}
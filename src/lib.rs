@@ -1,3 +1,18 @@
 pub mod game;
+pub mod bench;
+pub mod bot;
 pub mod config;
+pub mod error;
+pub mod party;
+pub mod protocol;
+pub mod replay;
+pub mod save;
+pub mod scores;
+pub mod server;
+pub mod session;
+pub mod strategy;
+pub mod terminal;
+pub mod tournament;
+pub mod tui;
+pub mod versus;
 mod display;
@@ -15,27 +15,295 @@ limitations under the License.
 */
 
 use clap::{App, arg, ArgMatches};
-use minesweeper::{config::Config, game::Game};
+use std::path::PathBuf;
+
+use minesweeper::{bench, bot::{Bot, RandomBot}, config::Config, party::{self, Party}, protocol::Protocol, server::Server, strategy::Strategy, tournament, versus::{Rule, Versus}, replay::{Player, Replay}, save::SaveFile, scores::{self, ScoreTable}, session::Session, terminal::Terminal, tui::Tui};
 
 fn main() {
-    let config = Config::new(get_args());
+    let args = get_args();
+
+    if let Some(args) = args.subcommand_matches("replay") {
+        play_replay(args);
+        return;
+    }
+
+    if let Some(args) = args.subcommand_matches("bench") {
+        run_bench(args);
+        return;
+    }
+
+    if let Some(args) = args.subcommand_matches("tournament") {
+        run_tournament(args);
+        return;
+    }
+
+    if let Some(args) = args.subcommand_matches("serve") {
+        serve(args);
+        return;
+    }
+
+    if let Some(args) = args.subcommand_matches("versus") {
+        versus(args);
+        return;
+    }
 
-    if !config.is_ok() {
-        eprintln!("{}", config.unwrap_err());
+    if let Some(args) = args.subcommand_matches("party") {
+        play_party(args);
         return;
     }
-    let config = config.unwrap();
 
-    let mut game = Game::new(&config);
+    let scores_file = args.value_of("scores-file").map(PathBuf::from)
+        .unwrap_or_else(scores::default_path);
+
+    if args.is_present("scores") {
+        print_scores(&scores_file);
+        return;
+    }
+
+    let save = match args.value_of("load").map(SaveFile::read) {
+        Some(Ok(save)) => Some(save),
+        Some(Err(err)) => {
+            eprintln!("{}", err);
+            return;
+        }
+        None => None,
+    };
+
+    // saved game brings its own board config
+    let config = match &save {
+        Some(save) => Config {
+            implicit_chord: !args.is_present("no-implicit-chord"),
+            question_marks: args.is_present("question-marks"),
+            ..save.config.clone()
+        },
+        None => match Config::new(&args) {
+            Ok(config) => config,
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        },
+    };
+
+    let mut session = Session::new(&config);
+    session.set_scores_file(scores_file);
+
+    if let Some(save) = save {
+        if let Err(err) = session.restore(save) {
+            eprintln!("{}", err);
+            return;
+        }
+    }
+
+    if let Some(path) = args.value_of("record") {
+        if let Err(err) = session.record(path) {
+            eprintln!("{}", err);
+            return;
+        }
+    }
 
-    game.run()
+    if args.value_of("protocol").is_some() {
+        if let Err(err) = Protocol::new(session).run() {
+            eprintln!("{}", err);
+        }
+    } else if args.is_present("line") {
+        Terminal::new(session).run();
+    } else if let Err(err) = Tui::new(session).run() {
+        eprintln!("{}", err);
+    }
 }
 
-fn get_args() -> ArgMatches {
-    App::new("minesweeper")
-        .author("rev1e")
-        .about("tui minesweeper game implemented in rust")
-        .version("0.1.0")
+fn print_scores(path: &PathBuf) {
+    let table = match ScoreTable::read(path) {
+        Ok(table) => table,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+
+    let mut empty = true;
+    for difficulty in table.difficulties() {
+        println!("{}", table.format(difficulty, None));
+        empty = false;
+    }
+
+    if empty {
+        println!("no high scores yet");
+    }
+}
+
+fn play_replay(args: &ArgMatches) {
+    let replay = match Replay::read(args.value_of("file").unwrap()) {
+        Ok(replay) => replay,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+
+    let speed: f64 = match args.value_of("speed").unwrap().parse() {
+        Ok(speed) if speed > 0.0 => speed,
+        _ => {
+            eprintln!("speed: invalid number");
+            return;
+        }
+    };
+
+    let config = replay.start.config.clone();
+
+    match Player::new(&config, replay, speed, args.is_present("paused")) {
+        Ok(mut player) => player.run(),
+        Err(err) => eprintln!("{}", err),
+    }
+}
+
+fn run_bench(args: &ArgMatches) {
+    let config = match Config::new(args) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+
+    let games = match parse_games(args) {
+        Some(games) => games,
+        None => return,
+    };
+
+    print!("{}", bench::run(&config, games));
+}
+
+fn serve(args: &ArgMatches) {
+    let config = match Config::new(args) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+
+    let port = match parse_port(args) {
+        Some(port) => port,
+        None => return,
+    };
+
+    let result = Server::bind(port).and_then(|mut server| {
+        println!("listening on port {}, first connection plays", port);
+        server.run(&config)
+    });
+
+    if let Err(err) = result {
+        eprintln!("{}", err);
+    }
+}
+
+fn versus(args: &ArgMatches) {
+    let config = match Config::new(args) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+
+    let port = match parse_port(args) {
+        Some(port) => port,
+        None => return,
+    };
+
+    let rule: Rule = match args.value_of("rule").unwrap().parse() {
+        Ok(rule) => rule,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+
+    let result = Versus::bind(port, rule).and_then(|mut versus| {
+        println!("listening on port {}, waiting for two players", port);
+        versus.run(&config)
+    });
+
+    if let Err(err) = result {
+        eprintln!("{}", err);
+    }
+}
+
+fn play_party(args: &ArgMatches) {
+    let config = match Config::new(args) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+
+    let rule: party::Rule = match args.value_of("rule").unwrap().parse() {
+        Ok(rule) => rule,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+
+    let names: Vec<String> = args.value_of("players").unwrap()
+        .split(',')
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    if names.len() < 2 {
+        eprintln!("players: at least two names are needed");
+        return;
+    }
+
+    Party::new(Session::new(&config), names, rule).run();
+}
+
+fn parse_port(args: &ArgMatches) -> Option<u16> {
+    let port = args.value_of("port").unwrap().parse().ok();
+    if port.is_none() {
+        eprintln!("port: invalid number");
+    }
+    port
+}
+
+fn parse_games(args: &ArgMatches) -> Option<u64> {
+    let games = args.value_of("games").unwrap().parse().ok();
+    if games.is_none() {
+        eprintln!("games: invalid number");
+    }
+    games
+}
+
+// built-in strategies against each other
+fn run_tournament(args: &ArgMatches) {
+    let config = match Config::new(args) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+
+    let games = match parse_games(args) {
+        Some(games) => games,
+        None => return,
+    };
+
+    let mut strategies: Vec<Box<dyn Strategy>> = vec![
+        Box::new(Bot::new()),
+        Box::new(RandomBot::new(config.seed)),
+    ];
+
+    print!("{}", tournament::run(&config, games, &mut strategies));
+}
+
+// options Config::new reads, shared by the game and bench
+fn config_args(app: App) -> App {
+    app
         .arg(
             arg!(-w --width <width> "Width")
                 .required(false)
@@ -51,5 +319,110 @@ fn get_args() -> ArgMatches {
                 .required(false)
                 .default_value("10")
             )
+        .arg(
+            arg!(-d --density <percent> "Percentage of cells that are mines, instead of --mines")
+                .required(false)
+            )
+        .arg(
+            arg!(-p --preset <preset> "beginner (9x9, 10), intermediate (16x16, 40) or expert (30x16, 99)")
+                .required(false)
+            )
+        .arg(
+            arg!(-s --seed <seed> "Seed for board generation")
+                .required(false)
+            )
+        .arg(arg!(--"no-guess" "Only generate boards solvable without guessing"))
+        .arg(arg!(--"no-implicit-chord" "Revealing a revealed number doesn't chord it, use chord"))
+        .arg(arg!(--"question-marks" "Flagging a flag turns it into a question mark"))
+}
+
+fn get_args() -> ArgMatches {
+    let app = App::new("minesweeper")
+        .author("rev1e")
+        .about("tui minesweeper game implemented in rust")
+        .version("0.1.0");
+
+    config_args(app)
+        .arg(
+            arg!(-l --load <file> "Continue a saved game")
+                .required(false)
+            )
+        .arg(
+            arg!(--record <file> "Record all moves into a replay file")
+                .required(false)
+            )
+        .arg(arg!(--line "Line based mode, moves are typed as coordinates"))
+        .arg(
+            arg!(--protocol <protocol> "Read commands from stdin and answer on stdout, for bots")
+                .required(false)
+                .possible_values(["json"])
+            )
+        .arg(arg!(--scores "Print the high score table"))
+        .arg(
+            arg!(--"scores-file" <file> "High score file")
+                .required(false)
+            )
+        .subcommand(
+            App::new("replay")
+                .about("Play back a recorded game")
+                .arg(arg!(<file> "Replay file"))
+                .arg(
+                    arg!(--speed <speed> "Playback speed multiplier")
+                        .required(false)
+                        .default_value("1")
+                    )
+                .arg(arg!(--paused "Start paused, step with n"))
+            )
+        .subcommand(
+            config_args(App::new("bench"))
+                .about("Let the built-in bot play boards seed, seed + 1, ... and report how it did")
+                .arg(
+                    arg!(-n --games <games> "Number of games")
+                        .required(false)
+                        .default_value("100")
+                    )
+            )
+        .subcommand(
+            config_args(App::new("tournament"))
+                .about("Let the built-in strategies play the same boards and compare them")
+                .arg(
+                    arg!(-n --games <games> "Number of games per strategy")
+                        .required(false)
+                        .default_value("100")
+                    )
+            )
+        .subcommand(
+            config_args(App::new("serve"))
+                .about("Host games over tcp, the first connection plays and the rest watch")
+                .arg(
+                    arg!(--port <port> "Port to listen on")
+                        .required(false)
+                        .default_value("7878")
+                    )
+            )
+        .subcommand(
+            config_args(App::new("versus"))
+                .about("Race two players over tcp on the same board")
+                .arg(
+                    arg!(--port <port> "Port to listen on")
+                        .required(false)
+                        .default_value("7878")
+                    )
+                .arg(
+                    arg!(--rule <rule> "What a mine does: out, or penalty:<seconds>")
+                        .required(false)
+                        .default_value("penalty:10")
+                    )
+            )
+        .subcommand(
+            config_args(App::new("party"))
+                .about("Take turns on one board in the terminal, a point per safe cell")
+                .arg(arg!(--players <names> "Comma separated player names"))
+                .arg(
+                    arg!(--rule <rule> "What a mine does: out, or minus:<points>")
+                        .required(false)
+                        .default_value("out")
+                    )
+            )
         .get_matches()
 }
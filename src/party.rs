@@ -0,0 +1,234 @@
+/*
+Copyright 2022 rev1e
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+   http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+// Hot seat mode: players take turns on one board in the line based
+// terminal. Revealing, chording or revealing all possible is a turn and
+// scores a point per safe cell it opens. Flags don't end the turn and are
+// shown in the color of the player who placed them. Hitting a mine takes
+// the move back and then eliminates the player or costs points.
+
+use std::{fmt, str::FromStr};
+
+use colored::Colorize;
+
+use crate::{display::{Display, player_color}, game::{Action, Status, cell::CellType, position::Position}, session::Session};
+
+// what hitting a mine does
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Rule {
+    Out,
+    Minus(u32),
+}
+
+// "out" or "minus:<points>"
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        if input == "out" {
+            return Ok(Rule::Out);
+        }
+
+        input.strip_prefix("minus:")
+            .and_then(|points| points.parse().ok())
+            .map(Rule::Minus)
+            .ok_or_else(|| format!("unknown rule '{}', use out or minus:<points>", input))
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rule::Out => write!(f, "mine eliminates"),
+            Rule::Minus(points) => write!(f, "mine costs {} points", points),
+        }
+    }
+}
+
+struct Player {
+    name: String,
+    score: i64,
+    out: bool,
+}
+
+pub struct Party<'a> {
+    session: Session<'a>,
+    display: Display<'a>,
+    players: Vec<Player>,
+    rule: Rule,
+    turn: usize,
+    // who placed the flag on a cell, row major
+    owners: Vec<Option<usize>>,
+    message: String,
+}
+
+impl<'a> Party<'a> {
+    pub fn new(session: Session<'a>, names: Vec<String>, rule: Rule) -> Self {
+        let config = session.game().config();
+
+        Self {
+            display: Display::new(config),
+            owners: vec![None; config.width * config.height],
+            session,
+            players: names.into_iter().map(|name| Player { name, score: 0, out: false }).collect(),
+            rule,
+            turn: 0,
+            message: String::new(),
+        }
+    }
+
+    pub fn run(&mut self) {
+        while self.session.game().status() == Status::Normal && self.players.iter().any(|p| !p.out) {
+            self.display.clear_screen();
+            self.display.render_owned_flags(self.session.game().cells(), &self.owners);
+            self.print_scores();
+
+            if !self.message.is_empty() {
+                println!("{}", self.message);
+                self.message.clear();
+            }
+
+            let prompt = format!("{} ({} mines left) -> ",
+                player_color(&self.players[self.turn].name, self.turn), self.session.game().mines_left());
+            let input = match self.display.prompt(&prompt) {
+                Some(input) => input.to_lowercase(),
+                None => break,
+            };
+
+            match input.as_str() {
+                "" => continue,
+                "quit" | "exit" | "q" => break,
+                _ => self.play(&input),
+            }
+        }
+
+        self.display.clear_screen();
+        self.display.render_board(self.session.game().cells(), None);
+        self.print_scores();
+        self.print_winner();
+
+        if let Err(err) = self.session.finish() {
+            eprintln!("failed to write replay: {}", err);
+        }
+    }
+
+    fn play(&mut self, input: &str) {
+        let action = match self.session.game().parse_action(input) {
+            Ok(Action::Undo | Action::Redo) => {
+                self.message = "no undo in a party".to_string();
+                return;
+            },
+            Ok(action) => action,
+            Err(err) => {
+                self.message = format!("[ERR] {}", err.to_string().red());
+                return;
+            },
+        };
+
+        let before = self.revealed();
+
+        let outcome = match self.session.play(action) {
+            Ok(outcome) => outcome,
+            Err(err) => {
+                self.message = format!("[ERR] {}", err.to_string().red());
+                return;
+            },
+        };
+
+        if let Action::Flag(pos) = action {
+            let owner = &mut self.owners[pos.y * self.session.game().config().width + pos.x];
+            *owner = self.session.game().cells().idx(pos).flag.then_some(self.turn);
+            return;
+        }
+
+        if outcome.status == Status::Loss {
+            // board goes on for the others
+            let _ = self.session.play(Action::Undo);
+
+            let player = &mut self.players[self.turn];
+            self.message = match self.rule {
+                Rule::Out => {
+                    player.out = true;
+                    format!("{} hit a mine and is out", player.name)
+                },
+                Rule::Minus(points) => {
+                    player.score -= points as i64;
+                    format!("{} hit a mine, -{}", player.name, points)
+                },
+            };
+        } else {
+            let points = self.revealed() - before;
+            let player = &mut self.players[self.turn];
+            player.score += points as i64;
+            self.message = format!("{} +{}", player.name, points);
+        }
+
+        self.next_turn();
+    }
+
+    fn next_turn(&mut self) {
+        for _ in 0..self.players.len() {
+            self.turn = (self.turn + 1) % self.players.len();
+            if !self.players[self.turn].out {
+                return;
+            }
+        }
+    }
+
+    // safe cells revealed on the board
+    fn revealed(&self) -> usize {
+        let config = self.session.game().config();
+        (0..config.height)
+            .flat_map(|y| (0..config.width).map(move |x| Position::new(x, y)))
+            .map(|pos| self.session.game().cells().idx(pos))
+            .filter(|cell| !cell.hidden && cell.ctype != CellType::Mine)
+            .count()
+    }
+
+    // alice 12 | bob 7 (out), current player underlined
+    fn print_scores(&self) {
+        let scores: Vec<String> = self.players.iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let mut name = player_color(&p.name, i);
+                if i == self.turn && self.session.game().status() == Status::Normal {
+                    name = name.underline();
+                }
+                format!("{} {}{}", name, p.score, if p.out { " (out)" } else { "" })
+            })
+            .collect();
+
+        println!("{} | {}", scores.join(" | "), self.rule);
+    }
+
+    fn print_winner(&self) {
+        let best = self.players.iter().map(|p| p.score).max().unwrap_or(0);
+        let winners: Vec<&str> = self.players.iter()
+            .filter(|p| p.score == best)
+            .map(|p| p.name.as_str())
+            .collect();
+
+        if self.session.game().status() == Status::Win {
+            println!("{}", "!!! BOARD CLEARED !!!".bright_green());
+        }
+
+        if winners.len() == 1 {
+            println!("{} wins with {} points", winners[0], best);
+        } else {
+            println!("draw between {} with {} points", winners.join(", "), best);
+        }
+    }
+}
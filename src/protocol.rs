@@ -0,0 +1,228 @@
+/*
+Copyright 2022 rev1e
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+   http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+// Line based JSON protocol for bots, one object per line in each direction.
+//
+// Commands on stdin:
+//     {"op":"reveal","x":3,"y":4}
+//     {"op":"flag","x":3,"y":4}
+//     {"op":"chord","x":3,"y":4}
+//     {"op":"possible"}
+//     {"op":"undo"}
+//     {"op":"redo"}
+//     {"op":"board"}
+//     {"op":"quit"}
+//
+// Every command is answered on stdout with the cells it changed:
+//     {"ok":true,"status":"normal","mines_left":9,"changed":[{"x":3,"y":4,"cell":"2"}]}
+//
+// A refused or malformed command keeps the board and adds the reason:
+//     {"ok":false,"error":"A0 is flagged","status":"normal","mines_left":9,"changed":[]}
+//
+// "board" answers with every cell and the board size. It is also sent
+// once at startup. Status is "normal", "win" or "loss", a cell is one of
+// "hidden", "flag", "question", "mine" or the number "0" to "8".
+// The game can still be undone after it ended, stdin closing or "quit"
+// ends the session.
+
+use std::io::{self, BufRead, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::ParseError, game::{Action, Status, Outcome, position::Position}, session::Session, strategy::{BoardView, Visible}};
+
+#[derive(Deserialize)]
+struct Request {
+    op: String,
+    x: Option<usize>,
+    y: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct Response {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    status: &'static str,
+    mines_left: i32,
+    // only answering "board"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    width: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mines: Option<u32>,
+    changed: Vec<CellState>,
+}
+
+#[derive(Serialize)]
+struct CellState {
+    x: usize,
+    y: usize,
+    cell: String,
+}
+
+// drives a session from json commands on stdin
+pub struct Protocol<'a> {
+    session: Session<'a>,
+}
+
+impl<'a> Protocol<'a> {
+    pub fn new(session: Session<'a>) -> Self {
+        Self { session }
+    }
+
+    pub fn run(&mut self) -> io::Result<()> {
+        let result = self.serve();
+
+        if let Err(err) = self.session.finish() {
+            eprintln!("failed to write replay: {}", err);
+        }
+
+        match result {
+            // the other program is gone, same as closing stdin
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+            result => result,
+        }
+    }
+
+    fn serve(&mut self) -> io::Result<()> {
+        self.send(self.board())?;
+
+        for line in io::stdin().lock().lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let request: Request = match serde_json::from_str(&line) {
+                Ok(request) => request,
+                Err(err) => {
+                    self.send(self.error(err.to_string()))?;
+                    continue;
+                }
+            };
+
+            let response = match request.op.as_str() {
+                "quit" => break,
+                "board" => self.board(),
+                _ => match self.action(&request) {
+                    Ok(action) => match self.session.play(action) {
+                        Ok(outcome) => self.outcome(outcome),
+                        Err(err) => self.error(err.to_string()),
+                    },
+                    Err(err) => self.error(err.to_string()),
+                },
+            };
+
+            self.send(response)?;
+        }
+
+        Ok(())
+    }
+
+    fn action(&self, request: &Request) -> Result<Action, ParseError> {
+        let action: fn(Position) -> Action = match request.op.as_str() {
+            "possible" => return Ok(Action::RevealPossible),
+            "undo" => return Ok(Action::Undo),
+            "redo" => return Ok(Action::Redo),
+            "reveal" => Action::Reveal,
+            "flag" => Action::Flag,
+            "chord" => Action::Chord,
+            op => return Err(ParseError::UnknownCommand(op.to_string())),
+        };
+
+        let (x, y) = match (request.x, request.y) {
+            (Some(x), Some(y)) => (x, y),
+            _ => return Err(ParseError::MissingCoordinates),
+        };
+
+        let config = self.session.game().config();
+        if x >= config.width {
+            return Err(ParseError::ColumnOutOfRange { max: config.width - 1, got: x });
+        }
+        if y >= config.height {
+            return Err(ParseError::RowOutOfRange { max: config.height - 1, got: y });
+        }
+
+        Ok(action(Position::new(x, y)))
+    }
+
+    fn outcome(&self, outcome: Outcome) -> Response {
+        self.response(true, outcome.changed)
+    }
+
+    fn error(&self, error: String) -> Response {
+        let mut response = self.response(false, Vec::new());
+        response.error = Some(error);
+        response
+    }
+
+    fn board(&self) -> Response {
+        let config = self.session.game().config();
+        let cells = (0..config.height)
+            .flat_map(|y| (0..config.width).map(move |x| Position::new(x, y)))
+            .collect();
+
+        let mut response = self.response(true, cells);
+        response.width = Some(config.width);
+        response.height = Some(config.height);
+        response.mines = Some(config.mines);
+        response
+    }
+
+    fn response(&self, ok: bool, changed: Vec<Position>) -> Response {
+        let game = self.session.game();
+        let view = BoardView::new(game);
+
+        let changed = changed.into_iter()
+            .map(|pos| CellState {
+                x: pos.x,
+                y: pos.y,
+                cell: match view.get(pos) {
+                    Visible::Hidden => "hidden".to_string(),
+                    Visible::Flag => "flag".to_string(),
+                    Visible::Question => "question".to_string(),
+                    Visible::Mine => "mine".to_string(),
+                    Visible::Number(n) => n.to_string(),
+                },
+            })
+            .collect();
+
+        Response {
+            ok,
+            error: None,
+            status: match game.status() {
+                Status::Normal => "normal",
+                Status::Win => "win",
+                Status::Loss => "loss",
+            },
+            mines_left: game.mines_left(),
+            width: None,
+            height: None,
+            mines: None,
+            changed,
+        }
+    }
+
+    // fails when the other program stopped reading
+    fn send(&self, response: Response) -> io::Result<()> {
+        let line = serde_json::to_string(&response).map_err(io::Error::from)?;
+        let mut out = io::stdout().lock();
+        writeln!(out, "{}", line)?;
+        out.flush()
+    }
+}
@@ -0,0 +1,260 @@
+/*
+Copyright 2022 rev1e
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+   http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+// Replay file format, version 1:
+//
+//     minesweeper-replay 1
+//     1520 reveal E5
+//     3210 flag A0
+//     4002 chord D4
+//     5000 possible
+//     end
+//     minesweeper-save 1
+//     ...
+//
+// Every move line is the time in milliseconds since the recording started
+// followed by the move. After "end" follows the board the recording started
+// from in save file format (see save.rs), with the full mine layout filled in.
+
+use std::{fs, io, path::Path, sync::mpsc::{self, RecvTimeoutError}, thread, time::{Duration, Instant}};
+
+use colored::Colorize;
+
+use crate::{config::Config, display::Display, error::SaveError, game::{Action, Game, Status, position::Position}, save::SaveFile};
+
+const HEADER: &str = "minesweeper-replay";
+const VERSION: u32 = 1;
+
+#[derive(Debug, Clone)]
+pub struct Step {
+    // time since start of the recording
+    pub time: Duration,
+    pub action: Action,
+}
+
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub start: SaveFile,
+    pub steps: Vec<Step>,
+}
+
+impl Replay {
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, SaveError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    pub fn parse(text: &str) -> Result<Self, SaveError> {
+        let (moves, board) = text.split_once("\nend\n")
+            .ok_or(SaveError::MissingField("end"))?;
+        let mut lines = moves.lines();
+
+        let header = lines.next().unwrap_or("");
+        if header != format!("{} {}", HEADER, VERSION) {
+            return Err(SaveError::BadHeader(header.to_string()));
+        }
+
+        let mut steps = Vec::new();
+        for line in lines {
+            let invalid = || SaveError::InvalidValue { field: "move", value: line.to_string() };
+
+            let (time, action) = line.split_once(' ').ok_or_else(invalid)?;
+            steps.push(Step {
+                time: Duration::from_millis(time.parse().map_err(|_| invalid())?),
+                action: action.parse().map_err(|_| invalid())?,
+            });
+        }
+
+        Ok(Self { start: SaveFile::parse(board)?, steps })
+    }
+}
+
+impl std::fmt::Display for Replay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} {}", HEADER, VERSION)?;
+        for step in &self.steps {
+            writeln!(f, "{} {}", step.time.as_millis(), step.action)?;
+        }
+        writeln!(f, "end")?;
+        write!(f, "{}", self.start)
+    }
+}
+
+// records accepted moves of a game
+pub struct Recorder {
+    start: SaveFile,
+    started: Instant,
+    steps: Vec<Step>,
+}
+
+impl Recorder {
+    // start recording from the current state of a running game
+    pub fn new(game: &Game) -> Result<Self, SaveError> {
+        Ok(Self {
+            start: game.to_save()?,
+            started: Instant::now(),
+            steps: Vec::new(),
+        })
+    }
+
+    pub fn record(&mut self, action: Action) {
+        self.steps.push(Step { time: self.started.elapsed(), action });
+    }
+
+    // mines are placed on the first reveal, so the layout is only
+    // known at the end and is copied into the starting board
+    pub fn finish(self, game: &Game) -> Replay {
+        let mut start = self.start;
+        let config = game.config();
+
+        for y in 0..config.height {
+            for x in 0..config.width {
+                start.cells[y * config.width + x].ctype = game.cells().idx(Position::new(x, y)).ctype;
+            }
+        }
+        start.generated = game.cells().generated();
+        start.config.seed = game.seed();
+
+        Replay { start, steps: self.steps }
+    }
+}
+
+// controls read from stdin while a replay plays
+enum Control {
+    Pause,
+    Step,
+    Faster,
+    Slower,
+    Quit,
+}
+
+// plays a replay back in the terminal
+pub struct Player<'a> {
+    game: Game<'a>,
+    display: Display<'a>,
+    steps: Vec<Step>,
+    speed: f64,
+    paused: bool,
+}
+
+impl<'a> Player<'a> {
+    // config has to be the config of replay.start
+    pub fn new(config: &'a Config, replay: Replay, speed: f64, paused: bool) -> Result<Self, SaveError> {
+        let mut game = Game::new(config);
+        game.restore(replay.start)?;
+
+        Ok(Self {
+            game,
+            display: Display::new(config),
+            steps: replay.steps,
+            speed,
+            paused,
+        })
+    }
+
+    pub fn run(&mut self) {
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut line = String::new();
+            while io::stdin().read_line(&mut line).map(|n| n > 0).unwrap_or(false) {
+                let control = match line.trim() {
+                    "" | "p" => Control::Pause,
+                    "n" => Control::Step,
+                    "+" => Control::Faster,
+                    "-" => Control::Slower,
+                    "q" => Control::Quit,
+                    _ => {
+                        line.clear();
+                        continue;
+                    }
+                };
+
+                if tx.send(control).is_err() {
+                    break;
+                }
+                line.clear();
+            }
+        });
+
+        let mut next = 0;
+        let mut last_time = Duration::ZERO;
+
+        loop {
+            self.render(next);
+
+            if next == self.steps.len() {
+                break;
+            }
+
+            let wait = (self.steps[next].time.saturating_sub(last_time)).div_f64(self.speed);
+            let control = if self.paused {
+                // nobody can unpause once stdin is closed
+                rx.recv().ok()
+            } else {
+                match rx.recv_timeout(wait) {
+                    Ok(control) => Some(control),
+                    Err(RecvTimeoutError::Timeout) => Some(Control::Step),
+                    Err(RecvTimeoutError::Disconnected) => {
+                        thread::sleep(wait);
+                        Some(Control::Step)
+                    }
+                }
+            };
+
+            match control {
+                Some(Control::Pause) => self.paused = !self.paused,
+                Some(Control::Faster) => self.speed *= 2.0,
+                Some(Control::Slower) => self.speed /= 2.0,
+                Some(Control::Quit) => break,
+                Some(Control::Step) => {
+                    let step = &self.steps[next];
+                    // moves were accepted when recorded
+                    let _ = self.game.apply(step.action);
+                    last_time = step.time;
+                    next += 1;
+                },
+                // stdin closed while paused, play to the end
+                None => self.paused = false,
+            }
+        }
+    }
+
+    fn render(&self, next: usize) {
+        self.display.clear_screen();
+        self.display.render_board(self.game.cells(), None);
+
+        if next > 0 {
+            println!("move {}/{}: {}", next, self.steps.len(), self.steps[next - 1].action);
+        } else {
+            println!("move 0/{}", self.steps.len());
+        }
+
+        match self.game.status() {
+            Status::Win => println!("{}", "!!! YOU WON !!!".bright_green()),
+            Status::Loss => println!("{}", "!!! GAME OVER !!!".red()),
+            Status::Normal => {
+                let state = if self.paused { "paused" } else { "playing" };
+                println!("speed x{} ({})", self.speed, state);
+                println!("enter/p -> pause, n -> next move, + -> faster, - -> slower, q -> quit");
+            }
+        }
+    }
+}
@@ -0,0 +1,228 @@
+/*
+Copyright 2022 rev1e
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+   http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+// Save file format, version 2. Plain text, one "key value" pair per line:
+//
+//     minesweeper-save 2
+//     width 10
+//     height 10
+//     mines 10
+//     seed 1234
+//     no_guess false
+//     question_marks false
+//     generated true
+//     guess_free false
+//     mines_left 9
+//     elapsed_ms 15230
+//     assisted false
+//     hints 0
+//     clicks 0
+//     board
+//     ##..1#
+//     ...
+//
+// After the "board" line follow `height` rows of `width` characters:
+//     #  hidden cell
+//     *  hidden mine
+//     !  flagged cell
+//     X  flagged mine
+//     ?  question marked cell
+//     Q  question marked mine
+//     .  revealed cell
+// Numbers are not stored, they are recalculated from the mines.
+// Before the first reveal (generated false) the board holds no mines.
+// "question_marks" is the flag cycle the game was played with, so replays
+// play back the same, and may be missing (false).
+// "guess_free" is true for no-guess boards checked to be solvable by
+// deduction and may be missing (false).
+// "assisted" is true when undo was used and may be missing (false).
+// "hints" counts hints asked for and "clicks" moves made, both may be missing (0).
+// Version 1 is the same format without question marks and can still be read.
+
+use std::{collections::HashMap, fs, path::Path, time::Duration, str::FromStr};
+
+use crate::{config::Config, error::SaveError, game::cell::{Cell, CellType}};
+
+const HEADER: &str = "minesweeper-save";
+const VERSION: u32 = 2;
+
+#[derive(Debug, Clone)]
+pub struct SaveFile {
+    pub config: Config,
+    // row major, numbers are stored as empty cells
+    pub cells: Vec<Cell>,
+    pub generated: bool,
+    pub guess_free: bool,
+    pub mines_left: i32,
+    pub elapsed: Duration,
+    // undo was used
+    pub assisted: bool,
+    pub hints: u32,
+    pub clicks: u32,
+}
+
+impl SaveFile {
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, SaveError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    pub fn parse(text: &str) -> Result<Self, SaveError> {
+        let mut lines = text.lines();
+
+        let header = lines.next().unwrap_or("");
+        if !(1..=VERSION).any(|v| header == format!("{} {}", HEADER, v)) {
+            return Err(SaveError::BadHeader(header.to_string()));
+        }
+
+        let mut fields = HashMap::new();
+        for line in lines.by_ref() {
+            if line == "board" {
+                break;
+            }
+
+            let (key, value) = line.split_once(' ')
+                .ok_or_else(|| SaveError::BadBoard(format!("unexpected line '{}'", line)))?;
+            fields.insert(key, value);
+        }
+
+        let config = Config {
+            width: field(&fields, "width")?,
+            height: field(&fields, "height")?,
+            mines: field(&fields, "mines")?,
+            seed: field(&fields, "seed")?,
+            no_guess: field(&fields, "no_guess")?,
+            // refused chords aren't recorded, so this one doesn't matter
+            implicit_chord: true,
+            question_marks: match fields.get("question_marks") {
+                Some(_) => field(&fields, "question_marks")?,
+                None => false,
+            },
+        };
+        config.validate().map_err(SaveError::Config)?;
+
+        let generated = field(&fields, "generated")?;
+        let mines_left: i32 = field(&fields, "mines_left")?;
+        let elapsed = Duration::from_millis(field(&fields, "elapsed_ms")?);
+        let guess_free = match fields.get("guess_free") {
+            Some(_) => field(&fields, "guess_free")?,
+            None => false,
+        };
+        let assisted = match fields.get("assisted") {
+            Some(_) => field(&fields, "assisted")?,
+            None => false,
+        };
+        let hints = match fields.get("hints") {
+            Some(_) => field(&fields, "hints")?,
+            None => 0,
+        };
+        let clicks = match fields.get("clicks") {
+            Some(_) => field(&fields, "clicks")?,
+            None => 0,
+        };
+
+        let rows: Vec<&str> = lines.collect();
+        if rows.len() != config.height {
+            return Err(SaveError::BadBoard(format!("expected {} rows, got {}", config.height, rows.len())));
+        }
+
+        let mut cells = Vec::with_capacity(config.width * config.height);
+        for (y, row) in rows.iter().enumerate() {
+            if row.chars().count() != config.width {
+                return Err(SaveError::BadBoard(format!("row {} is not {} cells wide", y, config.width)));
+            }
+
+            for c in row.chars() {
+                let (hidden, flag, question, mine) = match c {
+                    '#' => (true, false, false, false),
+                    '*' => (true, false, false, true),
+                    '!' => (true, true, false, false),
+                    'X' => (true, true, false, true),
+                    '?' => (true, false, true, false),
+                    'Q' => (true, false, true, true),
+                    '.' => (false, false, false, false),
+                    _ => return Err(SaveError::BadBoard(format!("unknown cell '{}' in row {}", c, y))),
+                };
+
+                let ctype = if mine { CellType::Mine } else { CellType::Empty };
+                cells.push(Cell { hidden, flag, question, ctype });
+            }
+        }
+
+        let mines = cells.iter().filter(|c| c.ctype == CellType::Mine).count() as u32;
+        let flags = cells.iter().filter(|c| c.flag).count() as i32;
+
+        if generated && mines != config.mines {
+            return Err(SaveError::BadBoard(format!("expected {} mines, got {}", config.mines, mines)));
+        }
+        if !generated && (mines != 0 || cells.iter().any(|c| !c.hidden)) {
+            return Err(SaveError::BadBoard("board without mines has revealed cells or mines".to_string()));
+        }
+        if mines_left != config.mines as i32 - flags {
+            return Err(SaveError::InvalidValue { field: "mines_left", value: mines_left.to_string() });
+        }
+
+        Ok(Self { config, cells, generated, guess_free, mines_left, elapsed, assisted, hints, clicks })
+    }
+}
+
+impl std::fmt::Display for SaveFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} {}", HEADER, VERSION)?;
+        writeln!(f, "width {}", self.config.width)?;
+        writeln!(f, "height {}", self.config.height)?;
+        writeln!(f, "mines {}", self.config.mines)?;
+        writeln!(f, "seed {}", self.config.seed)?;
+        writeln!(f, "no_guess {}", self.config.no_guess)?;
+        writeln!(f, "question_marks {}", self.config.question_marks)?;
+        writeln!(f, "generated {}", self.generated)?;
+        writeln!(f, "guess_free {}", self.guess_free)?;
+        writeln!(f, "mines_left {}", self.mines_left)?;
+        writeln!(f, "elapsed_ms {}", self.elapsed.as_millis())?;
+        writeln!(f, "assisted {}", self.assisted)?;
+        writeln!(f, "hints {}", self.hints)?;
+        writeln!(f, "clicks {}", self.clicks)?;
+        writeln!(f, "board")?;
+
+        for row in self.cells.chunks(self.config.width) {
+            for cell in row {
+                let mine = cell.ctype == CellType::Mine;
+                let c = match (cell.hidden, cell.flag, cell.question, mine) {
+                    (false, _, _, _) => '.',
+                    (true, true, _, false) => '!',
+                    (true, true, _, true) => 'X',
+                    (true, false, true, false) => '?',
+                    (true, false, true, true) => 'Q',
+                    (true, false, false, false) => '#',
+                    (true, false, false, true) => '*',
+                };
+                write!(f, "{}", c)?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn field<T: FromStr>(fields: &HashMap<&str, &str>, name: &'static str) -> Result<T, SaveError> {
+    let value = fields.get(name).ok_or(SaveError::MissingField(name))?;
+    value.parse().map_err(|_| SaveError::InvalidValue { field: name, value: value.to_string() })
+}
@@ -0,0 +1,224 @@
+/*
+Copyright 2022 rev1e
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+   http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+// High score file, version 1:
+//
+//     minesweeper-scores 1
+//     <width> <height> <mines> <time_ms> <unix_date> <name>
+//     ...
+//
+// Name is the rest of the line. The file is rewritten through a temporary
+// file and a rename while holding an os lock on <file>.lock, so a reader
+// never sees a half written table and concurrent games don't lose each
+// other's scores. The lock file stays, a game that crashes loses its lock.
+
+use std::{collections::BTreeMap, env, fmt, fs::{self, File, OpenOptions, TryLockError}, path::{Path, PathBuf}, thread, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+use crate::{config::Config, display::format_time, error::ScoreError};
+
+const HEADER: &str = "minesweeper-scores 1";
+// best times kept per difficulty
+const MAX_SCORES: usize = 10;
+const LOCK_ATTEMPTS: u32 = 100;
+const LOCK_WAIT: Duration = Duration::from_millis(20);
+
+// width, height, mines
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Difficulty {
+    pub width: usize,
+    pub height: usize,
+    pub mines: u32,
+}
+
+impl From<&Config> for Difficulty {
+    fn from(config: &Config) -> Self {
+        Self { width: config.width, height: config.height, mines: config.mines }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Score {
+    pub time: Duration,
+    // seconds since unix epoch
+    pub date: u64,
+    pub name: String,
+}
+
+impl Score {
+    // score dated now
+    pub fn new(time: Duration, name: &str) -> Self {
+        let date = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        Self { time, date, name: name.to_string() }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ScoreTable {
+    scores: BTreeMap<Difficulty, Vec<Score>>,
+}
+
+impl ScoreTable {
+    // missing file is an empty table
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, ScoreError> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn parse(text: &str) -> Result<Self, ScoreError> {
+        let mut lines = text.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(ScoreError::Corrupt { line: 1 });
+        }
+
+        let mut table = Self::default();
+
+        for (i, line) in lines.enumerate() {
+            let corrupt = || ScoreError::Corrupt { line: i + 2 };
+            let fields: Vec<&str> = line.splitn(6, ' ').collect();
+
+            if fields.len() != 6 {
+                return Err(corrupt());
+            }
+
+            let difficulty = Difficulty {
+                width: fields[0].parse().map_err(|_| corrupt())?,
+                height: fields[1].parse().map_err(|_| corrupt())?,
+                mines: fields[2].parse().map_err(|_| corrupt())?,
+            };
+            let score = Score {
+                time: Duration::from_millis(fields[3].parse().map_err(|_| corrupt())?),
+                date: fields[4].parse().map_err(|_| corrupt())?,
+                name: fields[5].to_string(),
+            };
+
+            table.insert(difficulty, score);
+        }
+
+        Ok(table)
+    }
+
+    pub fn difficulties(&self) -> impl Iterator<Item = Difficulty> + '_ {
+        self.scores.keys().copied()
+    }
+
+    pub fn scores(&self, difficulty: Difficulty) -> &[Score] {
+        self.scores.get(&difficulty).map(|s| s.as_slice()).unwrap_or(&[])
+    }
+
+    // rank a score with this time would get, None if it doesn't make the table
+    pub fn rank(&self, difficulty: Difficulty, time: Duration) -> Option<usize> {
+        let rank = self.scores(difficulty).iter().filter(|s| s.time <= time).count();
+        (rank < MAX_SCORES).then_some(rank)
+    }
+
+    // returns rank of the new score, None if it didn't make the table
+    pub fn insert(&mut self, difficulty: Difficulty, score: Score) -> Option<usize> {
+        let rank = self.rank(difficulty, score.time)?;
+        let scores = self.scores.entry(difficulty).or_default();
+
+        scores.insert(rank, score);
+        scores.truncate(MAX_SCORES);
+
+        Some(rank)
+    }
+
+    // table of one difficulty, rank is highlighted
+    pub fn format(&self, difficulty: Difficulty, highlight: Option<usize>) -> String {
+        let mut result = format!("{}x{}, {} mines:\n", difficulty.width, difficulty.height, difficulty.mines);
+
+        for (i, score) in self.scores(difficulty).iter().enumerate() {
+            let marker = if highlight == Some(i) { ">" } else { " " };
+            result += &format!("{}{:2}. {}  {}  {}\n",
+                marker, i + 1, format_time(score.time), format_date(score.date), score.name);
+        }
+
+        result
+    }
+}
+
+impl fmt::Display for ScoreTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+
+        for (difficulty, scores) in &self.scores {
+            for score in scores {
+                writeln!(f, "{} {} {} {} {} {}", difficulty.width, difficulty.height,
+                    difficulty.mines, score.time.as_millis(), score.date, score.name)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// ~/.minesweeper_scores, or the current directory without a home
+pub fn default_path() -> PathBuf {
+    match env::var_os("HOME") {
+        Some(home) => Path::new(&home).join(".minesweeper_scores"),
+        None => PathBuf::from("minesweeper_scores"),
+    }
+}
+
+// add score to the file. returns the updated table and rank of the score
+pub fn add_score(path: &Path, difficulty: Difficulty, score: Score) -> Result<(ScoreTable, Option<usize>), ScoreError> {
+    let _lock = acquire_lock(&path.with_extension("lock"))?;
+
+    let mut table = ScoreTable::read(path)?;
+    let rank = table.insert(difficulty, score);
+
+    if rank.is_some() {
+        // rename is atomic, readers see the old or the new file
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&tmp, table.to_string())?;
+        fs::rename(&tmp, path)?;
+    }
+
+    Ok((table, rank))
+}
+
+// locked until the returned file is closed
+fn acquire_lock(lock: &Path) -> Result<File, ScoreError> {
+    let file = OpenOptions::new().write(true).create(true).truncate(false).open(lock)?;
+
+    for _ in 0..LOCK_ATTEMPTS {
+        match file.try_lock() {
+            Ok(()) => return Ok(file),
+            Err(TryLockError::WouldBlock) => thread::sleep(LOCK_WAIT),
+            Err(TryLockError::Error(err)) => return Err(err.into()),
+        }
+    }
+
+    Err(ScoreError::Locked)
+}
+
+// unix time to yyyy-mm-dd (utc)
+fn format_date(secs: u64) -> String {
+    // days to civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = (secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
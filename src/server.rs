@@ -0,0 +1,241 @@
+/*
+Copyright 2022 rev1e
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+   http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+// Plain TCP game server. The first connection plays, every later one
+// watches. When the player leaves the next connection takes over.
+// Everyone gets the whole screen again after every move, so any line
+// based client like nc or telnet will do.
+//
+// Player commands, one per line:
+//     <pos>, reveal <pos>    reveal
+//     f <pos>, flag <pos>    flag
+//     c <pos>, chord <pos>   chord
+//     r, possible            reveal all possible
+//     u, undo / redo         take back a move / play it again
+//     new                    new board with a random seed
+//     quit                   disconnect
+
+use std::{io::{self, BufRead, BufReader, Write}, net::{Shutdown, TcpListener, TcpStream}, sync::mpsc::{self, Receiver, Sender}, thread, time::Duration};
+
+use colored::Colorize;
+
+use crate::{config::Config, display::{Display, format_time}, game::{Game, Status}, session::Session};
+
+// a client that takes longer to accept a screen is dropped,
+// so one stalled connection can't hold up the game
+pub(crate) const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub(crate) enum Event {
+    Connected(TcpStream),
+    Line(usize, String),
+    Closed(usize),
+}
+
+struct Client {
+    id: usize,
+    stream: TcpStream,
+}
+
+pub struct Server {
+    events: Receiver<Event>,
+    // reader threads send their lines here
+    sender: Sender<Event>,
+    // first one plays
+    clients: Vec<Client>,
+    next_id: usize,
+    message: String,
+}
+
+impl Server {
+    pub fn bind(port: u16) -> io::Result<Self> {
+        let (sender, events) = listen(port)?;
+        Ok(Self { events, sender, clients: Vec::new(), next_id: 0, message: String::new() })
+    }
+
+    // host games until the process is stopped. "new" starts the next one
+    pub fn run(&mut self, config: &Config) -> io::Result<()> {
+        let mut config = config.clone();
+
+        loop {
+            self.message = format!("new game, seed {}", config.seed);
+            self.play(&config)?;
+            config.seed = rand::random();
+        }
+    }
+
+    // returns when the player asks for a new game
+    fn play(&mut self, config: &Config) -> io::Result<()> {
+        let mut session = Session::new(config);
+        let display = Display::new(config);
+
+        self.broadcast(&display, &session);
+
+        loop {
+            // can't fail, the server holds a sender itself
+            match self.events.recv().unwrap() {
+                Event::Connected(stream) => {
+                    self.connect(stream);
+                },
+                Event::Closed(id) => {
+                    self.clients.retain(|c| c.id != id);
+                },
+                Event::Line(id, line) => {
+                    if self.clients.first().map(|c| c.id) != Some(id) {
+                        self.send_to(id, "only the player can make moves\r\n");
+                        continue;
+                    }
+
+                    let line = line.trim().to_lowercase();
+                    match line.as_str() {
+                        "" => continue,
+                        "new" => return Ok(()),
+                        "quit" => self.disconnect(id),
+                        _ => {
+                            self.message = match session.game().parse_action(&line) {
+                                Ok(action) => match session.play(action) {
+                                    Ok(_) => String::new(),
+                                    Err(err) => err.to_string(),
+                                },
+                                Err(err) => err.to_string(),
+                            };
+                        },
+                    }
+                },
+            }
+
+            self.broadcast(&display, &session);
+        }
+    }
+
+    // a connection that fails to set up is dropped, the game goes on
+    fn connect(&mut self, stream: TcpStream) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let setup = stream.set_write_timeout(Some(WRITE_TIMEOUT))
+            .and_then(|_| spawn_reader(id, &stream, self.sender.clone()));
+
+        if let Err(err) = setup {
+            eprintln!("dropped connection: {}", err);
+            return;
+        }
+
+        if let Ok(addr) = stream.peer_addr() {
+            println!("{} connected", addr);
+        }
+        self.clients.push(Client { id, stream });
+    }
+
+    fn disconnect(&mut self, id: usize) {
+        if let Some(client) = self.clients.iter().find(|c| c.id == id) {
+            let _ = client.stream.shutdown(Shutdown::Both);
+        }
+        self.clients.retain(|c| c.id != id);
+    }
+
+    fn send_to(&mut self, id: usize, text: &str) {
+        let failed = match self.clients.iter_mut().find(|c| c.id == id) {
+            Some(client) => client.stream.write_all(text.as_bytes()).is_err(),
+            None => false,
+        };
+
+        if failed {
+            self.disconnect(id);
+        }
+    }
+
+    // whole screen to every client. clients that can't be written to are dropped
+    fn broadcast(&mut self, display: &Display, session: &Session) {
+        let game = session.game();
+        let mut screen = board_screen(display, game);
+
+        screen += &match game.status() {
+            Status::Normal => format!("({} mines left, {})", game.mines_left(), format_time(game.elapsed())),
+            Status::Win => format!("{} time: {}", "!!! YOU WON !!!".bright_green(), format_time(game.elapsed())),
+            Status::Loss => format!("{} time: {}", "!!! GAME OVER !!!".red(), format_time(game.elapsed())),
+        };
+        screen += &format!(" {} watching\r\n", self.clients.len().saturating_sub(1));
+
+        if !self.message.is_empty() {
+            screen += &format!("{}\r\n", self.message);
+        }
+
+        let mut failed = Vec::new();
+        for (i, client) in self.clients.iter_mut().enumerate() {
+            let role = if i == 0 {
+                "you play: <pos>, f <pos>, c <pos>, r, undo, redo, new, quit\r\n-> "
+            } else {
+                "watching\r\n"
+            };
+
+            if client.stream.write_all(screen.as_bytes()).and_then(|_| client.stream.write_all(role.as_bytes())).is_err() {
+                failed.push(client.id);
+            }
+        }
+
+        for id in failed {
+            self.disconnect(id);
+        }
+    }
+}
+
+// listen on all interfaces. connections come in as events
+pub(crate) fn listen(port: u16) -> io::Result<(Sender<Event>, Receiver<Event>)> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    let (sender, events) = mpsc::channel();
+
+    let connections = sender.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if connections.send(Event::Connected(stream)).is_err() {
+                break;
+            }
+        }
+    });
+
+    Ok((sender, events))
+}
+
+// thread that sends every line of a connection as an event
+pub(crate) fn spawn_reader(id: usize, stream: &TcpStream, sender: Sender<Event>) -> io::Result<()> {
+    let reader = BufReader::new(stream.try_clone()?);
+
+    thread::spawn(move || {
+        for line in reader.lines() {
+            match line {
+                Ok(line) => if sender.send(Event::Line(id, line)).is_err() {
+                    return;
+                },
+                Err(_) => break,
+            }
+        }
+        let _ = sender.send(Event::Closed(id));
+    });
+
+    Ok(())
+}
+
+// clear the screen and draw the board, lines end in \r\n for terminals
+pub(crate) fn board_screen(display: &Display, game: &Game) -> String {
+    let mut screen = format!("{esc}[2J{esc}[1;1H", esc = 27 as char);
+
+    for line in display.board_lines(game.cells()) {
+        screen += &line;
+        screen += "\r\n";
+    }
+
+    screen
+}
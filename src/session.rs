@@ -0,0 +1,113 @@
+/*
+Copyright 2022 rev1e
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+   http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::path::PathBuf;
+
+use crate::{config::Config, error::{MoveError, SaveError, ScoreError}, game::{Action, Game, Outcome, solver::Hint}, replay::Recorder, save::SaveFile, scores::{self, Difficulty, Score, ScoreTable}};
+
+// game together with everything around it that frontends share:
+// replay recording, save files and high scores
+pub struct Session<'a> {
+    game: Game<'a>,
+    // replay file and its recorder
+    recording: Option<(String, Recorder)>,
+    scores_file: PathBuf,
+}
+
+impl<'a> Session<'a> {
+    pub fn new(config: &'a Config) -> Self {
+        Self {
+            game: Game::new(config),
+            recording: None,
+            scores_file: scores::default_path(),
+        }
+    }
+
+    pub fn game(&self) -> &Game<'a> {
+        &self.game
+    }
+
+    pub fn set_scores_file(&mut self, path: PathBuf) {
+        self.scores_file = path;
+    }
+
+    // record all moves into a replay file written by finish
+    pub fn record(&mut self, path: &str) -> Result<(), SaveError> {
+        self.recording = Some((path.to_string(), Recorder::new(&self.game)?));
+        Ok(())
+    }
+
+    // continue a saved game
+    pub fn restore(&mut self, save: SaveFile) -> Result<(), SaveError> {
+        self.game.restore(save)?;
+
+        // replay continues from the loaded board
+        if let Some((_, recorder)) = &mut self.recording {
+            *recorder = Recorder::new(&self.game)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), SaveError> {
+        self.game.to_save()?.write(path)
+    }
+
+    pub fn load(&mut self, path: &str) -> Result<(), SaveError> {
+        self.restore(SaveFile::read(path)?)
+    }
+
+    // apply move and record it if it was accepted
+    pub fn play(&mut self, action: Action) -> Result<Outcome, MoveError> {
+        let outcome = self.game.apply(action)?;
+
+        if let Some((_, recorder)) = &mut self.recording {
+            recorder.record(action);
+        }
+
+        Ok(outcome)
+    }
+
+    pub fn hint(&mut self) -> Result<Option<Hint>, MoveError> {
+        self.game.hint()
+    }
+
+    // write the replay file if recording
+    pub fn finish(&mut self) -> Result<(), SaveError> {
+        match self.recording.take() {
+            Some((path, recorder)) => recorder.finish(&self.game).write(path),
+            None => Ok(()),
+        }
+    }
+
+    // does the elapsed time make the high score table.
+    // assisted games never do
+    pub fn places(&self) -> Result<bool, ScoreError> {
+        if self.game.assisted() {
+            return Ok(false);
+        }
+
+        let table = ScoreTable::read(&self.scores_file)?;
+        Ok(table.rank(Difficulty::from(self.game.config()), self.game.elapsed()).is_some())
+    }
+
+    // add elapsed time to the high score table.
+    // returns updated table and rank of the new score
+    pub fn add_score(&self, name: &str) -> Result<(ScoreTable, Option<usize>), ScoreError> {
+        let score = Score::new(self.game.elapsed(), name);
+        scores::add_score(&self.scores_file, Difficulty::from(self.game.config()), score)
+    }
+}
@@ -0,0 +1,160 @@
+/*
+Copyright 2022 rev1e
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+   http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use crate::game::{Action, Game, Status, cell::CellType, position::Position, probability::Probabilities, solver::{Deduction, Solver}};
+
+// moves a game may take per cell before the strategy is stopped
+const MOVES_PER_CELL: u32 = 4;
+// refused moves in a row before the strategy is stopped
+const MAX_REJECTED: u32 = 100;
+
+// cell as the player sees it
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Visible {
+    Hidden,
+    Flag,
+    Question,
+    // mines around, 0 for an empty cell
+    Number(u32),
+    // only after the game is lost
+    Mine,
+}
+
+// read-only view of a game that shows no more than the screen does
+pub struct BoardView<'g> {
+    game: &'g Game<'g>,
+}
+
+impl<'g> BoardView<'g> {
+    pub fn new(game: &'g Game<'g>) -> Self {
+        Self { game }
+    }
+
+    pub fn width(&self) -> usize {
+        self.game.config().width
+    }
+
+    pub fn height(&self) -> usize {
+        self.game.config().height
+    }
+
+    pub fn mines(&self) -> u32 {
+        self.game.config().mines
+    }
+
+    pub fn mines_left(&self) -> i32 {
+        self.game.mines_left()
+    }
+
+    pub fn status(&self) -> Status {
+        self.game.status()
+    }
+
+    // mines are placed on the first reveal
+    pub fn started(&self) -> bool {
+        self.game.cells().generated()
+    }
+
+    pub fn get(&self, pos: Position) -> Visible {
+        let cell = self.game.cells().idx(pos);
+
+        if cell.flag {
+            Visible::Flag
+        } else if cell.question && cell.hidden {
+            Visible::Question
+        } else if cell.hidden {
+            Visible::Hidden
+        } else {
+            match cell.ctype {
+                CellType::Number(n) => Visible::Number(n),
+                CellType::Empty => Visible::Number(0),
+                CellType::Mine => Visible::Mine,
+            }
+        }
+    }
+
+    pub fn neighbours(&self, pos: Position) -> Vec<Position> {
+        pos.neighbours(self.width(), self.height())
+    }
+
+    // what the built-in solver proves from the visible numbers
+    pub fn deductions(&self) -> Vec<Deduction> {
+        Solver::new(self.game.cells()).solve()
+    }
+
+    // chance of a mine per hidden cell, trusting the flags
+    pub fn probabilities(&self) -> Option<Probabilities> {
+        self.game.probabilities()
+    }
+}
+
+// player that picks the next move from the visible board
+pub trait Strategy {
+    fn name(&self) -> &str;
+
+    // called before every game
+    fn start(&mut self) {}
+
+    fn next_action(&mut self, view: &BoardView) -> Action;
+}
+
+// result of one game played by a strategy
+#[derive(Debug, Clone)]
+pub struct PlayResult {
+    // Normal when the strategy was stopped
+    pub status: Status,
+    pub moves: u32,
+    // moves the game refused, and undo or redo
+    pub rejected: u32,
+    // share of safe cells revealed before the last move
+    pub progress: f64,
+}
+
+// let a strategy play until the game ends. a strategy that keeps making
+// refused moves or runs past the move limit is stopped
+pub fn play(game: &mut Game, strategy: &mut dyn Strategy) -> PlayResult {
+    let config = game.config();
+    let limit = (config.width * config.height) as u32 * MOVES_PER_CELL;
+
+    let mut result = PlayResult { status: Status::Normal, moves: 0, rejected: 0, progress: 0.0 };
+    let mut rejected_in_row = 0;
+
+    // taking moves back isn't part of the game for bots
+    game.set_history(false);
+    strategy.start();
+
+    while game.status() == Status::Normal && result.moves < limit && rejected_in_row < MAX_REJECTED {
+        result.progress = game.progress();
+
+        let action = strategy.next_action(&BoardView::new(game));
+
+        let accepted = match action {
+            Action::Undo | Action::Redo => false,
+            _ => game.apply(action).is_ok(),
+        };
+
+        if accepted {
+            result.moves += 1;
+            rejected_in_row = 0;
+        } else {
+            result.rejected += 1;
+            rejected_in_row += 1;
+        }
+    }
+
+    result.status = game.status();
+    result
+}
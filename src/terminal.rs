@@ -0,0 +1,226 @@
+/*
+Copyright 2022 rev1e
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+   http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::{env, error::Error};
+
+use colored::Colorize;
+
+use crate::{display::{Display, format_time}, game::{Action, Status, Outcome, position::Position, probability::Probabilities, stats::BoardStats}, error::{MoveError, ParseError}, scores::Difficulty, session::Session};
+
+enum EventType {
+    GameOver,
+    Win,
+    Info(String),
+    Error(Box<dyn Error>),
+}
+
+// line based terminal client for Game
+pub struct Terminal<'a> {
+    session: Session<'a>,
+    display: Display<'a>,
+    event: Option<EventType>,
+    // cell of the last hint, shown until the next command
+    marked: Option<Position>,
+    // mine probabilities shown instead of the board once
+    heatmap: Option<Probabilities>,
+}
+
+impl<'a> Terminal<'a> {
+    pub fn new(session: Session<'a>) -> Self {
+        Self {
+            display: Display::new(session.game().config()),
+            session,
+            event: None,
+            marked: None,
+            heatmap: None,
+        }
+    }
+
+    pub fn run(&mut self) {
+        self.game_loop();
+
+        if let Err(err) = self.session.finish() {
+            eprintln!("failed to write replay: {}", err);
+        }
+    }
+
+    fn game_loop(&mut self) {
+        // game loop
+        loop {
+            self.display.clear_screen();
+            match self.heatmap.take() {
+                Some(probs) => self.display.render_probabilities(self.session.game().cells(), &probs),
+                None => self.display.render_board(self.session.game().cells(), self.marked.take()),
+            }
+
+            // handle event
+            if let Some(etype) = self.event.take() {
+                match etype {
+                    EventType::Info(msg) => println!("{}", msg),
+                    EventType::Error(err) => println!("[ERR] {}", err.to_string().red()),
+                    EventType::Win => {
+                        print_result(&self.display, &self.session);
+                        break;
+                    },
+                    EventType::GameOver => {
+                        print_result(&self.display, &self.session);
+
+                        // losing reveal can be taken back
+                        let answer = self.display.prompt("undo the last move? [y/N] ");
+                        if answer.is_some_and(|answer| answer.eq_ignore_ascii_case("y")) {
+                            self.play(Action::Undo);
+                            continue;
+                        }
+                        break;
+                    },
+                }
+            }
+
+            // file names keep their case
+            let game = self.session.game();
+            let raw_input = self.display.get_input(game.mines_left(), game.elapsed());
+            let input = raw_input.to_lowercase();
+            
+            if input.is_empty() {
+                self.event = Some(EventType::Error(ParseError::Empty.into()));
+                continue;
+            }
+
+            let mut args_iter = input.split_whitespace();
+
+            match args_iter.next().unwrap() {
+                "quit" | "exit" | "q" => break,
+                "save" | "load" => {
+                    let path = match raw_input.split_whitespace().nth(1) {
+                        Some(path) => path,
+                        None => {
+                            self.event = Some(EventType::Error(ParseError::MissingFile.into()));
+                            continue;
+                        }
+                    };
+
+                    let result = if input.starts_with("save") {
+                        self.session.save(path).map(|_| format!("saved to {}", path))
+                    } else {
+                        self.session.load(path).map(|_| format!("loaded {}", path))
+                    };
+
+                    self.event = Some(match result {
+                        Ok(msg) => EventType::Info(msg),
+                        Err(err) => EventType::Error(err.into()),
+                    });
+                },
+                "hint" => {
+                    self.event = Some(match self.session.hint() {
+                        Ok(Some(hint)) => {
+                            self.marked = Some(hint.pos());
+                            EventType::Info(hint.to_string())
+                        },
+                        Ok(None) => EventType::Info("nothing to hint".to_string()),
+                        Err(err) => EventType::Error(err.into()),
+                    });
+                },
+                "probs" => {
+                    self.heatmap = self.session.game().probabilities();
+                    match &self.heatmap {
+                        None => self.event = Some(EventType::Info("no mine layout fits the board, check the flags".to_string())),
+                        Some(probs) if !probs.exact() => self.event = Some(EventType::Info("too many layouts to count, chances are estimated".to_string())),
+                        Some(_) => {},
+                    }
+                },
+                "help" | "?" | "h" => {
+                    self.display.print_help();
+                },
+                _ => match self.session.game().parse_action(&input) {
+                    Ok(action) => self.play(action),
+                    Err(err) => self.event = Some(EventType::Error(err.into())),
+                },
+            }
+        }
+    }
+
+    fn play(&mut self, action: Action) {
+        let outcome = self.session.play(action);
+        self.handle_outcome(outcome);
+    }
+
+    fn handle_outcome(&mut self, outcome: Result<Outcome, MoveError>) {
+        self.event = match outcome {
+            Ok(outcome) => match outcome.status {
+                Status::Normal => None,
+                Status::Win => Some(EventType::Win),
+                Status::Loss => Some(EventType::GameOver),
+            },
+            Err(err) => Some(EventType::Error(err.into())),
+        };
+    }
+}
+
+// end of game banner. a won game that makes the high score table
+// asks for the player's name
+pub(crate) fn print_result(display: &Display, session: &Session) {
+    let game = session.game();
+
+    match game.status() {
+        Status::Win => println!("{}", "!!! YOU WON !!!".bright_green()),
+        Status::Loss => println!("{}", "!!! GAME OVER !!!".red()),
+        Status::Normal => return,
+    }
+    println!("time: {}", format_time(game.elapsed()));
+    if !game.config().no_guess {
+        println!("seed: {}", game.seed());
+    } else if game.guess_free() {
+        println!("seed: {} (guess-free)", game.seed());
+    } else {
+        println!("seed: {} (no guess-free layout found, board may need guessing)", game.seed());
+    }
+    if let Some(stats) = BoardStats::new(game) {
+        println!("{}", stats);
+    }
+    if game.hints() > 0 {
+        println!("hints: {}", game.hints());
+    }
+
+    if game.status() != Status::Win {
+        return;
+    }
+
+    if game.assisted() {
+        println!("undo was used, game doesn't count for high scores");
+        return;
+    }
+
+    match session.places() {
+        Ok(true) => {},
+        Ok(false) => return,
+        Err(err) => {
+            println!("[ERR] {}", err.to_string().red());
+            return;
+        }
+    }
+
+    let default_name = env::var("USER").unwrap_or_else(|_| "player".to_string());
+    let name = display.prompt(&format!("new high score! name [{}]: ", default_name));
+    let name = match name {
+        Some(name) if !name.is_empty() => name,
+        _ => default_name,
+    };
+
+    match session.add_score(&name) {
+        Ok((table, rank)) => print!("{}", table.format(Difficulty::from(game.config()), rank)),
+        Err(err) => println!("[ERR] {}", err.to_string().red()),
+    }
+}
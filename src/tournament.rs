@@ -0,0 +1,114 @@
+/*
+Copyright 2022 rev1e
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+   http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::{cmp::Reverse, fmt};
+
+use crate::{config::Config, game::{Game, Status, position::Position}, strategy::{self, Strategy}};
+
+// how one strategy did over all boards
+#[derive(Debug, Clone)]
+pub struct Standing {
+    pub name: String,
+    pub wins: u32,
+    pub losses: u32,
+    // stopped for refused moves or the move limit
+    pub stopped: u32,
+    pub moves: u64,
+    pub rejected: u64,
+    // boards no other strategy won
+    pub only_wins: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct TournamentReport {
+    pub config: Config,
+    pub games: u64,
+    pub standings: Vec<Standing>,
+}
+
+// every strategy plays boards config.seed, config.seed + 1, ... in turn.
+// mines are placed around the first click, so the runner opens every
+// board at its center before handing over and all strategies get the
+// same layout and opening
+pub fn run(config: &Config, games: u64, strategies: &mut [Box<dyn Strategy>]) -> TournamentReport {
+    let mut standings: Vec<Standing> = strategies.iter()
+        .map(|s| Standing {
+            name: s.name().to_string(),
+            wins: 0,
+            losses: 0,
+            stopped: 0,
+            moves: 0,
+            rejected: 0,
+            only_wins: 0,
+        })
+        .collect();
+
+    for i in 0..games {
+        let config = Config { seed: config.seed.wrapping_add(i), ..config.clone() };
+        let mut winners = Vec::new();
+
+        for (n, strategy) in strategies.iter_mut().enumerate() {
+            let mut game = Game::new(&config);
+            // opening can win tiny boards
+            let _ = game.reveal(Position::new(config.width / 2, config.height / 2));
+
+            let result = strategy::play(&mut game, strategy.as_mut());
+            let standing = &mut standings[n];
+
+            match result.status {
+                Status::Win => {
+                    standing.wins += 1;
+                    winners.push(n);
+                },
+                Status::Loss => standing.losses += 1,
+                Status::Normal => standing.stopped += 1,
+            }
+            standing.moves += result.moves as u64;
+            standing.rejected += result.rejected as u64;
+        }
+
+        if let [n] = winners[..] {
+            standings[n].only_wins += 1;
+        }
+    }
+
+    TournamentReport { config: config.clone(), games, standings }
+}
+
+impl fmt::Display for TournamentReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let config = &self.config;
+
+        writeln!(f, "{}x{}, {} mines{}", config.width, config.height, config.mines,
+            if config.no_guess { ", no guess" } else { "" })?;
+        writeln!(f, "seeds {}..{}", config.seed, config.seed.wrapping_add(self.games))?;
+
+        let width = self.standings.iter().map(|s| s.name.len()).max().unwrap_or(0).max("strategy".len());
+        writeln!(f, "{:<w$} {:>6} {:>7} {:>7} {:>9} {:>9} {:>10}", "strategy", "wins", "win %", "only", "stopped", "refused", "avg moves", w = width)?;
+
+        let mut standings: Vec<&Standing> = self.standings.iter().collect();
+        standings.sort_by_key(|s| Reverse(s.wins));
+
+        for s in standings {
+            let games = self.games.max(1) as f64;
+            writeln!(f, "{:<w$} {:>6} {:>6.1}% {:>7} {:>9} {:>9} {:>10.1}",
+                s.name, s.wins, s.wins as f64 * 100.0 / games, s.only_wins, s.stopped, s.rejected,
+                s.moves as f64 / games, w = width)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,358 @@
+/*
+Copyright 2022 rev1e
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+   http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+use std::{io::{self, Write}, time::Duration};
+
+use colored::Colorize;
+use crossterm::{cursor, event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind}, queue, style::{Attribute, Print, SetAttribute}, terminal::{self, ClearType}};
+
+use crate::{display::{Display, format_time}, game::{Action, Status, position::Position, probability::Probabilities}, session::Session, terminal::print_result};
+
+// status line is refreshed this often so the timer keeps running
+const TICK: Duration = Duration::from_secs(1);
+
+// raw mode on the alternate screen, restored on drop
+struct Screen;
+
+impl Screen {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        // crossterm turns on xterm sgr mouse reporting
+        crossterm::execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide, EnableMouseCapture)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = crossterm::execute!(io::stdout(), DisableMouseCapture, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// mouse buttons held down
+#[derive(Default)]
+struct Buttons {
+    left: bool,
+    right: bool,
+    // both were down, chord on release
+    chord: bool,
+}
+
+// full screen terminal client. a cursor is moved over the board and
+// only cells that changed are redrawn
+pub struct Tui<'a> {
+    session: Session<'a>,
+    display: Display<'a>,
+    cursor: Position,
+    message: String,
+    buttons: Buttons,
+    // mine probabilities drawn over hidden cells while turned on
+    heatmap: Option<Probabilities>,
+}
+
+impl<'a> Tui<'a> {
+    pub fn new(session: Session<'a>) -> Self {
+        let config = session.game().config();
+
+        Self {
+            display: Display::new(config),
+            cursor: Position::new(config.width / 2, config.height / 2),
+            session,
+            message: String::new(),
+            buttons: Buttons::default(),
+            heatmap: None,
+        }
+    }
+
+    pub fn run(&mut self) -> io::Result<()> {
+        let screen = Screen::enter()?;
+        let quit = self.event_loop()?;
+        drop(screen);
+
+        // final board stays on the normal screen
+        if !quit {
+            self.display.render_board(self.session.game().cells(), None);
+            print_result(&self.display, &self.session);
+        }
+
+        if let Err(err) = self.session.finish() {
+            eprintln!("failed to write replay: {}", err);
+        }
+
+        Ok(())
+    }
+
+    // returns true if the player quit before the game ended
+    fn event_loop(&mut self) -> io::Result<bool> {
+        self.draw_all()?;
+
+        loop {
+            if self.session.game().status() != Status::Normal {
+                // wait for a key so the final board can be seen.
+                // a lost game can still be undone
+                loop {
+                    if let Event::Key(key) = event::read()? {
+                        if key.code == KeyCode::Char('u') && self.session.game().status() == Status::Loss {
+                            self.play(Action::Undo)?;
+                            self.draw_all()?;
+                            break;
+                        }
+                        return Ok(false);
+                    }
+                }
+                continue;
+            }
+
+            if !event::poll(TICK)? {
+                self.draw_status()?;
+                continue;
+            }
+
+            match event::read()? {
+                Event::Key(key) if !self.handle_key(key)? => return Ok(true),
+                Event::Mouse(mouse) => self.handle_mouse(mouse)?,
+                Event::Resize(_, _) => self.draw_all()?,
+                _ => {},
+            }
+        }
+    }
+
+    // returns false when the player quits
+    fn handle_key(&mut self, key: KeyEvent) -> io::Result<bool> {
+        let config = self.session.game().config();
+        let old = self.cursor;
+        let mut action = None;
+
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(false),
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+            KeyCode::Left | KeyCode::Char('h') => self.cursor.x = self.cursor.x.saturating_sub(1),
+            KeyCode::Right | KeyCode::Char('l') => self.cursor.x = (self.cursor.x + 1).min(config.width - 1),
+            KeyCode::Up | KeyCode::Char('k') => self.cursor.y = self.cursor.y.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.cursor.y = (self.cursor.y + 1).min(config.height - 1),
+            KeyCode::Char(' ') | KeyCode::Enter => action = Some(Action::Reveal(self.cursor)),
+            KeyCode::Char('f') => action = Some(Action::Flag(self.cursor)),
+            KeyCode::Char('c') => action = Some(Action::Chord(self.cursor)),
+            KeyCode::Char('r') => action = Some(Action::RevealPossible),
+            KeyCode::Char('u') => action = Some(Action::Undo),
+            KeyCode::Char('U') => action = Some(Action::Redo),
+            KeyCode::Char('?') => self.hint()?,
+            KeyCode::Char('p') => self.toggle_heatmap()?,
+            _ => {},
+        }
+
+        if old != self.cursor {
+            self.draw_cell(old)?;
+            self.draw_cell(self.cursor)?;
+        }
+
+        if let Some(action) = action {
+            self.play(action)?;
+        }
+
+        self.draw_status()?;
+        Ok(true)
+    }
+
+    // left click reveals, right click flags. middle click or
+    // left and right together chord
+    fn handle_mouse(&mut self, mouse: MouseEvent) -> io::Result<()> {
+        let pos = match self.display.screen_to_cell(mouse.column as usize, mouse.row as usize) {
+            Some(pos) => pos,
+            None => {
+                // released outside of the board
+                if let MouseEventKind::Up(_) = mouse.kind {
+                    self.buttons = Buttons::default();
+                }
+                return Ok(());
+            }
+        };
+
+        let mut action = None;
+
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.buttons.left = true;
+                self.buttons.chord = self.buttons.right;
+            },
+            MouseEventKind::Down(MouseButton::Right) => {
+                self.buttons.right = true;
+                self.buttons.chord = self.buttons.left;
+
+                // right click on a number may be the start of a chord
+                if !self.buttons.chord && self.session.game().cells().idx(pos).hidden {
+                    action = Some(Action::Flag(pos));
+                }
+            },
+            MouseEventKind::Down(MouseButton::Middle) => action = Some(Action::Chord(pos)),
+            MouseEventKind::Up(button) => {
+                if self.buttons.chord {
+                    // chord once, on the first button released
+                    if self.buttons.left && self.buttons.right {
+                        action = Some(Action::Chord(pos));
+                    }
+                } else if button == MouseButton::Left && self.buttons.left {
+                    action = Some(Action::Reveal(pos));
+                }
+
+                match button {
+                    MouseButton::Left => self.buttons.left = false,
+                    MouseButton::Right => self.buttons.right = false,
+                    MouseButton::Middle => {},
+                }
+                if !self.buttons.left && !self.buttons.right {
+                    self.buttons.chord = false;
+                }
+            },
+            _ => return Ok(()),
+        }
+
+        let old = self.cursor;
+        self.cursor = pos;
+        if old != pos {
+            self.draw_cell(old)?;
+            self.draw_cell(pos)?;
+        }
+
+        if let Some(action) = action {
+            self.play(action)?;
+        }
+
+        self.draw_status()
+    }
+
+    fn play(&mut self, action: Action) -> io::Result<()> {
+        match self.session.play(action) {
+            Ok(outcome) => {
+                self.message.clear();
+
+                // any move can change every probability
+                if self.heatmap.is_some() {
+                    self.heatmap = self.session.game().probabilities();
+                    return self.draw_all();
+                }
+
+                for pos in outcome.changed {
+                    self.draw_cell(pos)?;
+                }
+            },
+            Err(err) => self.message = err.to_string().red().to_string(),
+        }
+
+        Ok(())
+    }
+
+    fn toggle_heatmap(&mut self) -> io::Result<()> {
+        if self.heatmap.take().is_none() {
+            self.heatmap = self.session.game().probabilities();
+            self.message = match self.heatmap {
+                Some(ref probs) if !probs.exact() => "S safe, M mine, 0-9 chance of a mine in tenths (estimated, too many layouts to count)".to_string(),
+                Some(_) => "S safe, M mine, 0-9 chance of a mine in tenths".to_string(),
+                None => "no mine layout fits the board, check the flags".red().to_string(),
+            };
+        } else {
+            self.message.clear();
+        }
+
+        self.draw_all()
+    }
+
+    // move the cursor to the hinted cell and explain it
+    fn hint(&mut self) -> io::Result<()> {
+        match self.session.hint() {
+            Ok(Some(hint)) => {
+                let old = self.cursor;
+                self.cursor = hint.pos();
+                self.draw_cell(old)?;
+                self.draw_cell(self.cursor)?;
+                self.message = hint.to_string();
+            },
+            Ok(None) => self.message = "nothing to hint".to_string(),
+            Err(err) => self.message = err.to_string().red().to_string(),
+        }
+
+        Ok(())
+    }
+
+    fn draw_all(&mut self) -> io::Result<()> {
+        let mut out = io::stdout();
+        queue!(out, terminal::Clear(ClearType::All))?;
+
+        for (i, line) in self.display.board_lines(self.session.game().cells()).iter().enumerate() {
+            queue!(out, cursor::MoveTo(0, i as u16), Print(line))?;
+        }
+
+        if self.heatmap.is_some() {
+            let config = self.session.game().config();
+            for y in 0..config.height {
+                for x in 0..config.width {
+                    self.draw_cell(Position::new(x, y))?;
+                }
+            }
+        }
+
+        self.draw_cell(self.cursor)?;
+        self.draw_status()
+    }
+
+    fn draw_cell(&self, pos: Position) -> io::Result<()> {
+        let mut out = io::stdout();
+        let (x, y) = self.display.cell_screen_pos(pos);
+        let cell = self.session.game().cells().idx(pos);
+        let cell = match &self.heatmap {
+            Some(probs) if self.session.game().status() == Status::Normal => self.display.heat_str(cell, probs.get(pos)),
+            _ => self.display.cell_str(cell),
+        };
+
+        queue!(out, cursor::MoveTo(x as u16, y as u16))?;
+
+        if pos == self.cursor && self.session.game().status() == Status::Normal {
+            queue!(out, SetAttribute(Attribute::Reverse), Print(cell), SetAttribute(Attribute::Reset))?;
+        } else {
+            queue!(out, Print(cell))?;
+        }
+
+        out.flush()
+    }
+
+    // mines left, time, last message and key help below the board
+    fn draw_status(&self) -> io::Result<()> {
+        let mut out = io::stdout();
+        let game = self.session.game();
+        let y = self.display.board_lines(game.cells()).len() as u16 + 1;
+
+        let status = match game.status() {
+            Status::Normal => format!("({} mines left, {}) {}",
+                game.mines_left(), format_time(game.elapsed()), self.message),
+            Status::Win => format!("{} press any key", "!!! YOU WON !!!".bright_green()),
+            Status::Loss => format!("{} u to undo, any other key to quit", "!!! GAME OVER !!!".red()),
+        };
+
+        let lines = [
+            status.as_str(),
+            "arrows/hjkl move, space/enter reveal, f flag, c chord, r reveal possible, q quit",
+            "u undo, U redo, ? hint, p mine probabilities",
+            "mouse: left reveal, right flag, middle or left+right chord",
+        ];
+
+        for (i, line) in lines.iter().enumerate() {
+            queue!(out, cursor::MoveTo(0, y + i as u16), terminal::Clear(ClearType::CurrentLine), Print(line))?;
+        }
+
+        out.flush()
+    }
+}
@@ -0,0 +1,315 @@
+/*
+Copyright 2022 rev1e
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+   http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+// Two player race over TCP. Both players connect with a line based
+// client like nc and get the same board, opened at its center. Moves are
+// typed as in the server (see server.rs), undo is not allowed. Whoever
+// clears the board in the shortest time wins. Hitting a mine either adds
+// a time penalty and takes the move back, or ends that player's run.
+
+use std::{fmt, io::{self, Write}, net::{Shutdown, TcpStream}, str::FromStr, sync::mpsc::{Receiver, RecvTimeoutError, Sender}, time::{Duration, Instant}};
+
+use colored::Colorize;
+
+use crate::{config::Config, display::{Display, format_time}, game::{Action, Game, Status, position::Position}, server::{self, Event}, save::SaveFile, session::Session};
+
+// clocks are redrawn this often
+const TICK: Duration = Duration::from_secs(1);
+// seeds tried for a board the opening doesn't clear
+const OPENING_ATTEMPTS: u32 = 100;
+
+// what hitting a mine does
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Rule {
+    // time added, the mine stays hidden
+    Penalty(Duration),
+    // run is over
+    Out,
+}
+
+// "out" or "penalty:<seconds>"
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        if input == "out" {
+            return Ok(Rule::Out);
+        }
+
+        input.strip_prefix("penalty:")
+            .and_then(|secs| secs.parse().ok())
+            .map(|secs| Rule::Penalty(Duration::from_secs(secs)))
+            .ok_or_else(|| format!("unknown rule '{}', use out or penalty:<seconds>", input))
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rule::Penalty(time) => write!(f, "mine costs {}s", time.as_secs()),
+            Rule::Out => write!(f, "mine ends the run"),
+        }
+    }
+}
+
+struct Racer<'a> {
+    // connection id, events of earlier connections are ignored
+    id: usize,
+    stream: TcpStream,
+    session: Session<'a>,
+    penalty: Duration,
+    // race time when the board was cleared
+    finished: Option<Duration>,
+    // race time when the run ended on a mine or the player left
+    out: Option<Duration>,
+    message: String,
+}
+
+impl<'a> Racer<'a> {
+    fn time(&self, start: Instant) -> Duration {
+        self.finished.or(self.out).unwrap_or_else(|| start.elapsed() + self.penalty)
+    }
+
+    fn running(&self) -> bool {
+        self.finished.is_none() && self.out.is_none()
+    }
+}
+
+pub struct Versus {
+    events: Receiver<Event>,
+    sender: Sender<Event>,
+    rule: Rule,
+    next_id: usize,
+}
+
+impl Versus {
+    pub fn bind(port: u16, rule: Rule) -> io::Result<Self> {
+        let (sender, events) = server::listen(port)?;
+        Ok(Self { events, sender, rule, next_id: 0 })
+    }
+
+    // wait for two players and race once
+    pub fn run(&mut self, config: &Config) -> io::Result<()> {
+        let save = opening(config)?;
+        let mut streams: Vec<(usize, TcpStream)> = Vec::new();
+
+        while streams.len() < 2 {
+            // can't fail, the race holds a sender itself
+            match self.events.recv().unwrap() {
+                Event::Connected(mut stream) => {
+                    let id = self.next_id;
+                    self.next_id += 1;
+
+                    // a connection that fails to set up is dropped, others keep waiting
+                    let setup = stream.set_write_timeout(Some(server::WRITE_TIMEOUT))
+                        .and_then(|_| server::spawn_reader(id, &stream, self.sender.clone()));
+
+                    if let Err(err) = setup {
+                        eprintln!("dropped connection: {}", err);
+                        continue;
+                    }
+
+                    let _ = stream.write_all(b"waiting for an opponent\r\n");
+                    streams.push((id, stream));
+                },
+                Event::Closed(id) => {
+                    // first player left before the start
+                    streams.retain(|(waiting, _)| *waiting != id);
+                },
+                _ => {},
+            }
+        }
+
+        let mut racers = Vec::new();
+        for (id, stream) in streams {
+            let mut session = Session::new(config);
+            session.restore(save.clone()).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+            racers.push(Racer { id, stream, session, penalty: Duration::ZERO, finished: None, out: None, message: String::new() });
+        }
+
+        let display = Display::new(config);
+        let start = Instant::now();
+
+        loop {
+            let winner = self.winner(&racers, start);
+
+            for i in 0..2 {
+                let screen = self.screen(&display, &racers, i, start, winner);
+                // a player that can't be reached anymore shows up as Closed,
+                // one that stalls is cut off and shows up the same way
+                if racers[i].stream.write_all(screen.as_bytes()).is_err() {
+                    let _ = racers[i].stream.shutdown(Shutdown::Both);
+                }
+            }
+
+            if winner.is_some() {
+                break;
+            }
+
+            let slot = |id| racers.iter().position(|r: &Racer| r.id == id);
+
+            let (i, line) = match self.events.recv_timeout(TICK) {
+                Ok(Event::Line(id, line)) => match slot(id) {
+                    Some(i) => (i, line),
+                    None => continue,
+                },
+                Ok(Event::Closed(id)) => {
+                    if let Some(i) = slot(id) {
+                        let racer = &mut racers[i];
+                        if racer.running() {
+                            racer.out = Some(racer.time(start));
+                        }
+                    }
+                    continue;
+                },
+                Ok(Event::Connected(mut stream)) => {
+                    let _ = stream.write_all(b"race is full\r\n");
+                    continue;
+                },
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => unreachable!(),
+            };
+
+            self.play(&mut racers[i], &line.trim().to_lowercase(), start);
+        }
+
+        for racer in racers {
+            let _ = racer.stream.shutdown(Shutdown::Both);
+        }
+
+        Ok(())
+    }
+
+    fn play(&self, racer: &mut Racer, line: &str, start: Instant) {
+        if !racer.running() || line.is_empty() {
+            return;
+        }
+
+        let action = match racer.session.game().parse_action(line) {
+            Ok(Action::Undo | Action::Redo) => {
+                racer.message = "no undo in a race".to_string();
+                return;
+            },
+            Ok(action) => action,
+            Err(err) => {
+                racer.message = err.to_string();
+                return;
+            },
+        };
+
+        racer.message = match racer.session.play(action) {
+            Ok(outcome) => match (outcome.status, self.rule) {
+                (Status::Normal, _) => String::new(),
+                (Status::Win, _) => {
+                    racer.finished = Some(start.elapsed() + racer.penalty);
+                    "board cleared".to_string()
+                },
+                (Status::Loss, Rule::Penalty(penalty)) => {
+                    // the mine stays hidden, flag it
+                    let _ = racer.session.play(Action::Undo);
+                    racer.penalty += penalty;
+                    format!("mine! +{}s", penalty.as_secs())
+                },
+                (Status::Loss, Rule::Out) => {
+                    racer.out = Some(racer.time(start));
+                    "mine! your run is over".to_string()
+                },
+            },
+            Err(err) => err.to_string(),
+        };
+    }
+
+    // Some(Some(i)) when player i won, Some(None) for a draw,
+    // None while the race goes on
+    fn winner(&self, racers: &[Racer], start: Instant) -> Option<Option<usize>> {
+        let (a, b) = (&racers[0], &racers[1]);
+
+        match (a.finished, b.finished) {
+            (Some(ta), Some(tb)) => return Some(if ta == tb { None } else if ta < tb { Some(0) } else { Some(1) }),
+            // the other can't be faster anymore
+            (Some(ta), None) if b.out.is_some() || b.time(start) > ta => return Some(Some(0)),
+            (None, Some(tb)) if a.out.is_some() || a.time(start) > tb => return Some(Some(1)),
+            _ => {},
+        }
+
+        if a.out.is_some() && b.out.is_some() {
+            let (pa, pb) = (a.session.game().progress(), b.session.game().progress());
+            return Some(if pa == pb { None } else if pa > pb { Some(0) } else { Some(1) });
+        }
+
+        None
+    }
+
+    fn screen(&self, display: &Display, racers: &[Racer], i: usize, start: Instant, winner: Option<Option<usize>>) -> String {
+        let (me, other) = (&racers[i], &racers[1 - i]);
+        let game = me.session.game();
+
+        let mut screen = server::board_screen(display, game);
+
+        screen += &format!("({} mines left) {}\r\n", game.mines_left(), self.rule);
+        screen += &format!("you: {}\r\nopponent: {}\r\n", progress_line(me, start), progress_line(other, start));
+
+        if !me.message.is_empty() {
+            screen += &format!("{}\r\n", me.message);
+        }
+
+        screen += &match winner {
+            Some(Some(w)) if w == i => format!("{}\r\n", "!!! YOU WON THE RACE !!!".bright_green()),
+            Some(Some(_)) => format!("{}\r\n", "!!! YOU LOST THE RACE !!!".red()),
+            Some(None) => "draw\r\n".to_string(),
+            None if me.running() => "-> ".to_string(),
+            None => "waiting for the opponent to finish\r\n".to_string(),
+        };
+
+        screen
+    }
+}
+
+// 45% cleared, 01:10 (+20s)
+// mines are placed around the first click, so the board is opened at its
+// center once and both players get a copy of it. when the opening clears
+// the whole board there is nothing left to race on and the next seed is tried
+fn opening(config: &Config) -> io::Result<SaveFile> {
+    let mut config = config.clone();
+
+    for _ in 0..OPENING_ATTEMPTS {
+        let mut game = Game::new(&config);
+        let _ = game.reveal(Position::new(config.width / 2, config.height / 2));
+
+        if let Ok(save) = game.to_save() {
+            return Ok(save);
+        }
+        config.seed = config.seed.wrapping_add(1);
+    }
+
+    Err(io::Error::new(io::ErrorKind::InvalidInput, "the opening clears every board, use more mines"))
+}
+
+fn progress_line(racer: &Racer, start: Instant) -> String {
+    let mut line = format!("{:.0}% cleared, {}", racer.session.game().progress() * 100.0, format_time(racer.time(start)));
+
+    if !racer.penalty.is_zero() {
+        line += &format!(" (+{}s)", racer.penalty.as_secs());
+    }
+    if racer.finished.is_some() {
+        line += ", done";
+    }
+    if racer.out.is_some() {
+        line += ", out";
+    }
+
+    line
+}